@@ -0,0 +1,81 @@
+use std::f64::consts::PI;
+
+use crate::hexagon::{Hex, HexMath, HexUtility};
+use crate::layout::{LayoutTool, TransformedLayout};
+use crate::point::Point;
+
+const ANGLE_EPSILON: f64 = 1e-9;
+
+/// Area-of-effect shapes around a caster hex.
+///
+/// Shapes contain the hexes at distance 1 to `radius` from the origin whose
+/// centers lie inside the angular span, edges included. Angles grow with the
+/// direction index, so direction `i` of `HEX_DIRECTIONS` is at `i * PI / 3`
+/// and a cone of width `PI / 3` covers one direction's sector.
+pub struct HexArea;
+
+impl HexArea {
+    /// A cone facing `direction`, `width` radians wide.
+    pub fn cone(origin: Hex, direction: i32, radius: i32, width: f64) -> impl Iterator<Item = Hex> {
+        let center: f64 = direction as f64 * PI / 3.0;
+        Self::span(origin, radius, center - width / 2.0, width, hex_angle)
+    }
+
+    /// A cone facing the pixel angle `angle` of the layout, `width` radians
+    /// wide, measured in pixel space.
+    pub fn cone_pixel(
+        layout: impl Into<TransformedLayout>,
+        origin: Hex,
+        angle: f64,
+        radius: i32,
+        width: f64,
+    ) -> impl Iterator<Item = Hex> {
+        let layout: TransformedLayout = layout.into();
+        let zero: Point = LayoutTool::hex_to_pixel(layout, Hex::new(0, 0));
+        let pixel_angle = move |delta: Hex| -> f64 {
+            let point: Point = LayoutTool::hex_to_pixel(layout, delta);
+            (point.y - zero.y).atan2(point.x - zero.x)
+        };
+
+        Self::span(origin, radius, angle - width / 2.0, width, pixel_angle)
+    }
+
+    /// The wedge turning from direction `from` through increasing indices to
+    /// direction `to`. Equal directions give the straight ray between them.
+    pub fn wedge(origin: Hex, from: i32, to: i32, radius: i32) -> impl Iterator<Item = Hex> {
+        let start: f64 = from as f64 * PI / 3.0;
+        let width: f64 = (to - from).rem_euclid(6) as f64 * PI / 3.0;
+        Self::span(origin, radius, start, width, hex_angle)
+    }
+
+    fn span<F>(
+        origin: Hex,
+        radius: i32,
+        start: f64,
+        width: f64,
+        angle: F,
+    ) -> impl Iterator<Item = Hex>
+    where
+        F: Fn(Hex) -> f64,
+    {
+        origin.range(radius).into_iter().filter(move |hex| {
+            let delta: Hex = hex.sub(origin);
+            if delta.length() == 0 {
+                return false;
+            }
+            if width >= 2.0 * PI - ANGLE_EPSILON {
+                return true;
+            }
+
+            let offset: f64 = (angle(delta) - start).rem_euclid(2.0 * PI);
+            offset <= width + ANGLE_EPSILON || offset >= 2.0 * PI - ANGLE_EPSILON
+        })
+    }
+}
+
+// Angle of a hex offset on the grid, direction `i` at `i * PI / 3`.
+fn hex_angle(delta: Hex) -> f64 {
+    let x: f64 = delta.q() as f64 + delta.r() as f64 / 2.0;
+    let y: f64 = -(delta.r() as f64) * 3f64.sqrt() / 2.0;
+    y.atan2(x)
+}
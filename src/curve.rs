@@ -0,0 +1,136 @@
+use crate::hexagon::Hex;
+
+/// Locality preserving `u64` keys for hexes.
+///
+/// Axial coordinates are shifted by 2^31 into unsigned values first, so the
+/// keys keep the order of the coordinates across zero.
+pub struct HexCurve;
+
+impl HexCurve {
+    /// Both coordinates in one `u64`, q in the high half. Cheap to hash and
+    /// compare, without any locality.
+    pub fn packed(hex: Hex) -> u64 {
+        ((to_unsigned(hex.q()) as u64) << 32) | to_unsigned(hex.r()) as u64
+    }
+
+    pub fn unpack(key: u64) -> Hex {
+        Hex::new(to_signed((key >> 32) as u32), to_signed(key as u32))
+    }
+
+    /// Z-order key interleaving the bits of q (even bits) and r (odd bits).
+    pub fn morton(hex: Hex) -> u64 {
+        spread(to_unsigned(hex.q())) | (spread(to_unsigned(hex.r())) << 1)
+    }
+
+    pub fn from_morton(key: u64) -> Hex {
+        Hex::new(to_signed(compact(key)), to_signed(compact(key >> 1)))
+    }
+
+    /// Position of the hex along a Hilbert curve covering the whole axial
+    /// plane. Neighboring keys are always neighboring (q, r) cells.
+    pub fn hilbert(hex: Hex) -> u64 {
+        let mut x: u32 = to_unsigned(hex.q());
+        let mut y: u32 = to_unsigned(hex.r());
+        let mut key: u64 = 0;
+
+        let mut s: u32 = 1 << 31;
+        while s > 0 {
+            let rx: u64 = (x & s != 0) as u64;
+            let ry: u64 = (y & s != 0) as u64;
+            key += (s as u64) * (s as u64) * ((3 * rx) ^ ry);
+            rotate(u32::MAX, &mut x, &mut y, rx, ry);
+            s >>= 1;
+        }
+
+        key
+    }
+
+    pub fn from_hilbert(key: u64) -> Hex {
+        let mut x: u64 = 0;
+        let mut y: u64 = 0;
+        let mut t: u64 = key;
+
+        let mut s: u64 = 1;
+        while s < 1 << 32 {
+            let rx: u64 = 1 & (t / 2);
+            let ry: u64 = 1 & (t ^ rx);
+            if ry == 0 {
+                if rx == 1 {
+                    x = s - 1 - x;
+                    y = s - 1 - y;
+                }
+                std::mem::swap(&mut x, &mut y);
+            }
+            x += s * rx;
+            y += s * ry;
+            t /= 4;
+            s *= 2;
+        }
+
+        Hex::new(to_signed(x as u32), to_signed(y as u32))
+    }
+
+    /// Sorted, merged inclusive Morton key intervals covering exactly `hexes`,
+    /// e.g. the result of `HexUtility::range`.
+    pub fn morton_ranges(hexes: &[Hex]) -> Vec<(u64, u64)> {
+        merge(hexes.iter().map(|hex| Self::morton(*hex)).collect())
+    }
+
+    /// Sorted, merged inclusive Hilbert key intervals covering exactly `hexes`.
+    pub fn hilbert_ranges(hexes: &[Hex]) -> Vec<(u64, u64)> {
+        merge(hexes.iter().map(|hex| Self::hilbert(*hex)).collect())
+    }
+}
+
+fn to_unsigned(value: i32) -> u32 {
+    (value as u32) ^ (1 << 31)
+}
+
+fn to_signed(value: u32) -> i32 {
+    (value ^ (1 << 31)) as i32
+}
+
+fn spread(value: u32) -> u64 {
+    let mut x: u64 = value as u64;
+    x = (x | (x << 16)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x << 8)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    x = (x | (x << 1)) & 0x5555_5555_5555_5555;
+    x
+}
+
+fn compact(key: u64) -> u32 {
+    let mut x: u64 = key & 0x5555_5555_5555_5555;
+    x = (x | (x >> 1)) & 0x3333_3333_3333_3333;
+    x = (x | (x >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x >> 4)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x >> 8)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x >> 16)) & 0x0000_0000_ffff_ffff;
+    x as u32
+}
+
+fn rotate(last: u32, x: &mut u32, y: &mut u32, rx: u64, ry: u64) {
+    if ry == 0 {
+        if rx == 1 {
+            *x = last - *x;
+            *y = last - *y;
+        }
+        std::mem::swap(x, y);
+    }
+}
+
+fn merge(mut keys: Vec<u64>) -> Vec<(u64, u64)> {
+    keys.sort_unstable();
+    keys.dedup();
+
+    let mut ranges: Vec<(u64, u64)> = vec![];
+    for key in keys {
+        match ranges.last_mut() {
+            Some(last) if last.1 + 1 == key => last.1 = key,
+            _ => ranges.push((key, key)),
+        }
+    }
+
+    ranges
+}
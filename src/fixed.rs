@@ -0,0 +1,255 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::hexagon::Hex;
+
+const FRACTION_BITS: u32 = 32;
+
+/// A signed Q32.32 fixed-point number.
+///
+/// Every operation is plain integer arithmetic, so results are identical on
+/// all targets. Multiplication and division round toward negative infinity.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Fixed(i64);
+
+impl Fixed {
+    pub const ZERO: Fixed = Fixed(0);
+    pub const ONE: Fixed = Fixed(1 << FRACTION_BITS);
+
+    pub const fn from_bits(bits: i64) -> Fixed {
+        Fixed(bits)
+    }
+
+    pub const fn to_bits(self) -> i64 {
+        self.0
+    }
+
+    pub const fn from_int(value: i32) -> Fixed {
+        Fixed((value as i64) << FRACTION_BITS)
+    }
+
+    /// `numerator / denominator` rounded to the nearest representable value.
+    pub fn from_ratio(numerator: i64, denominator: i64) -> Fixed {
+        let scaled: i128 = ((numerator as i128) << FRACTION_BITS) * 2;
+        let denominator: i128 = denominator as i128;
+        let sign: i128 = if (scaled < 0) != (denominator < 0) {
+            -1
+        } else {
+            1
+        };
+
+        Fixed(((scaled.abs() / denominator.abs() + 1) / 2 * sign) as i64)
+    }
+
+    /// Nearest fixed-point value, for converting from float data such as
+    /// sizes read from a configuration file.
+    pub fn from_f64(value: f64) -> Fixed {
+        Fixed((value * (1u64 << FRACTION_BITS) as f64).round() as i64)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / (1u64 << FRACTION_BITS) as f64
+    }
+
+    pub fn abs(self) -> Fixed {
+        Fixed(self.0.abs())
+    }
+
+    /// Nearest integer, halfway values rounded away from zero like `f64::round`.
+    pub fn round(self) -> i32 {
+        let half: i64 = 1 << (FRACTION_BITS - 1);
+        if self.0 < 0 {
+            -((-self.0 + half) >> FRACTION_BITS) as i32
+        } else {
+            ((self.0 + half) >> FRACTION_BITS) as i32
+        }
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: Fixed) -> Fixed {
+        Fixed(self.0 + other.0)
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0 - other.0)
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: Fixed) -> Fixed {
+        Fixed(((self.0 as i128 * other.0 as i128) >> FRACTION_BITS) as i64)
+    }
+}
+
+impl Div for Fixed {
+    type Output = Fixed;
+
+    fn div(self, other: Fixed) -> Fixed {
+        let numerator: i128 = self.0 as i128 * Fixed::ONE.0 as i128;
+        let denominator: i128 = other.0 as i128;
+        let quotient: i128 = numerator / denominator;
+
+        if numerator % denominator != 0 && (numerator < 0) != (denominator < 0) {
+            Fixed((quotient - 1) as i64)
+        } else {
+            Fixed(quotient as i64)
+        }
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Fixed {
+        Fixed(-self.0)
+    }
+}
+
+/// The square root of 3 rounded to the nearest Q32.32 value.
+pub const FIXED_SQRT_3: Fixed = Fixed::from_bits(7_439_101_574);
+
+const FIXED_SQRT_3_2: Fixed = Fixed::from_bits(3_719_550_787);
+const FIXED_SQRT_3_3: Fixed = Fixed::from_bits(2_479_700_525);
+const FIXED_THIRD: Fixed = Fixed::from_bits(1_431_655_765);
+const FIXED_TWO_THIRDS: Fixed = Fixed::from_bits(2_863_311_531);
+const FIXED_THREE_HALVES: Fixed = Fixed::from_bits(6_442_450_944);
+
+pub const FIXED_ORIENTATION_POINTY: FixedOrientation = FixedOrientation {
+    f0: FIXED_SQRT_3,
+    f1: FIXED_SQRT_3_2,
+    f2: Fixed::ZERO,
+    f3: FIXED_THREE_HALVES,
+    b0: FIXED_SQRT_3_3,
+    b1: Fixed::from_bits(-FIXED_THIRD.to_bits()),
+    b2: Fixed::ZERO,
+    b3: FIXED_TWO_THIRDS,
+};
+
+pub const FIXED_ORIENTATION_FLAT: FixedOrientation = FixedOrientation {
+    f0: FIXED_THREE_HALVES,
+    f1: Fixed::ZERO,
+    f2: FIXED_SQRT_3_2,
+    f3: FIXED_SQRT_3,
+    b0: FIXED_TWO_THIRDS,
+    b1: Fixed::ZERO,
+    b2: Fixed::from_bits(-FIXED_THIRD.to_bits()),
+    b3: FIXED_SQRT_3_3,
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct FixedPoint {
+    pub x: Fixed,
+    pub y: Fixed,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct FixedFractionalHex {
+    q: Fixed,
+    r: Fixed,
+    s: Fixed,
+}
+
+impl FixedFractionalHex {
+    pub fn new(q: Fixed, r: Fixed) -> FixedFractionalHex {
+        FixedFractionalHex { q, r, s: -q - r }
+    }
+
+    pub fn q(&self) -> Fixed {
+        self.q
+    }
+
+    pub fn r(&self) -> Fixed {
+        self.r
+    }
+
+    pub fn s(&self) -> Fixed {
+        self.s
+    }
+
+    /// Same rounding as `HexRound::round`.
+    pub fn round(&self) -> Hex {
+        let qi: i32 = self.q.round();
+        let ri: i32 = self.r.round();
+        let si: i32 = self.s.round();
+
+        let q_diff: Fixed = (Fixed::from_int(qi) - self.q).abs();
+        let r_diff: Fixed = (Fixed::from_int(ri) - self.r).abs();
+        let s_diff: Fixed = (Fixed::from_int(si) - self.s).abs();
+
+        if q_diff > r_diff && q_diff > s_diff {
+            Hex::new(-ri - si, ri)
+        } else if r_diff > s_diff {
+            Hex::new(qi, -qi - si)
+        } else {
+            Hex::new(qi, ri)
+        }
+    }
+}
+
+impl From<Hex> for FixedFractionalHex {
+    fn from(hex: Hex) -> FixedFractionalHex {
+        FixedFractionalHex::new(Fixed::from_int(hex.q()), Fixed::from_int(hex.r()))
+    }
+}
+
+/// `Orientation` in fixed point. There is no start angle, corners are not
+/// part of the deterministic math.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FixedOrientation {
+    pub f0: Fixed,
+    pub f1: Fixed,
+    pub f2: Fixed,
+    pub f3: Fixed,
+    pub b0: Fixed,
+    pub b1: Fixed,
+    pub b2: Fixed,
+    pub b3: Fixed,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FixedLayout {
+    pub orientation: FixedOrientation,
+    pub size: FixedPoint,
+    pub origin: FixedPoint,
+}
+
+/// `LayoutTool` for `FixedLayout`, giving bit-identical results on every
+/// target for lockstep simulations and replays.
+pub struct FixedLayoutTool;
+
+impl FixedLayoutTool {
+    pub fn hex_to_pixel(layout: FixedLayout, hex: Hex) -> FixedPoint {
+        let orientation: FixedOrientation = layout.orientation;
+        let q: Fixed = Fixed::from_int(hex.q());
+        let r: Fixed = Fixed::from_int(hex.r());
+
+        let x: Fixed = (orientation.f0 * q + orientation.f1 * r) * layout.size.x;
+        let y: Fixed = (orientation.f2 * q + orientation.f3 * r) * layout.size.y;
+
+        FixedPoint {
+            x: x + layout.origin.x,
+            y: y + layout.origin.y,
+        }
+    }
+
+    pub fn pixel_to_hex(layout: FixedLayout, point: FixedPoint) -> FixedFractionalHex {
+        let orientation: FixedOrientation = layout.orientation;
+        let pt: FixedPoint = FixedPoint {
+            x: (point.x - layout.origin.x) / layout.size.x,
+            y: (point.y - layout.origin.y) / layout.size.y,
+        };
+
+        let q: Fixed = orientation.b0 * pt.x + orientation.b1 * pt.y;
+        let r: Fixed = orientation.b2 * pt.x + orientation.b3 * pt.y;
+
+        FixedFractionalHex::new(q, r)
+    }
+}
@@ -0,0 +1,218 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::hexagon::Hex;
+use crate::tools::HexDirection;
+
+/// Distance map and flow field towards a set of goals over a bounded map.
+///
+/// The map is given as the cost of entering each passable hex, hexes missing
+/// from it are blocked. Costs are expected to be positive.
+#[derive(Clone, Debug)]
+pub struct FlowField {
+    costs: HashMap<Hex, i32>,
+    goals: HashSet<Hex>,
+    distances: HashMap<Hex, i32>,
+}
+
+impl FlowField {
+    pub fn new(costs: HashMap<Hex, i32>, goals: &[Hex]) -> FlowField {
+        let mut field = FlowField {
+            costs,
+            goals: goals.iter().copied().collect(),
+            distances: HashMap::new(),
+        };
+
+        let mut frontier: BinaryHeap<Reverse<(i32, i32, i32)>> = BinaryHeap::new();
+        let mut log: HashMap<Hex, Option<i32>> = HashMap::new();
+        for goal in goals {
+            if field.costs.contains_key(goal) {
+                field.assign(*goal, Some(0), &mut log);
+                frontier.push(Reverse((0, goal.q(), goal.r())));
+            }
+        }
+        field.relax(frontier, &mut log);
+
+        field
+    }
+
+    pub fn distances(&self) -> &HashMap<Hex, i32> {
+        &self.distances
+    }
+
+    /// Cost of the cheapest path from `hex` to any goal, the sum of the costs
+    /// of the hexes entered on the way. `None` when no goal can be reached.
+    pub fn distance(&self, hex: Hex) -> Option<i32> {
+        self.distances.get(&hex).copied()
+    }
+
+    /// Index into `HEX_DIRECTIONS` of the step to take from `hex` towards the
+    /// closest goal. `None` on goals and on hexes that cannot reach one.
+    /// Ties go to the lowest direction index.
+    pub fn direction(&self, hex: Hex) -> Option<i32> {
+        if self.goals.contains(&hex) {
+            return None;
+        }
+        self.distance(hex)?;
+
+        let mut best: Option<(i32, i32)> = None;
+        for direction in 0..6 {
+            let distance: i32 = match self.through(HexDirection::neighbor(hex, direction)) {
+                Some(distance) => distance,
+                None => continue,
+            };
+            if improves(best.map(|(known, _)| known), distance) {
+                best = Some((distance, direction));
+            }
+        }
+
+        best.map(|(_, direction)| direction)
+    }
+
+    /// Changes the cost of entering `hex`, `None` blocks it.
+    ///
+    /// Only the part of the field that depends on `hex` is recomputed. Returns
+    /// the hexes whose distance changed.
+    pub fn set_cost(&mut self, hex: Hex, cost: Option<i32>) -> Vec<Hex> {
+        let previous: Option<i32> = self.costs.get(&hex).copied();
+        if previous == cost {
+            return vec![];
+        }
+
+        match cost {
+            Some(cost) => self.costs.insert(hex, cost),
+            None => self.costs.remove(&hex),
+        };
+
+        let mut frontier: BinaryHeap<Reverse<(i32, i32, i32)>> = BinaryHeap::new();
+        let increased: bool = match (previous, cost) {
+            (Some(previous), Some(cost)) => cost > previous,
+            (_, None) => true,
+            (None, Some(_)) => false,
+        };
+
+        let mut log: HashMap<Hex, Option<i32>> = HashMap::new();
+        let mut reseed: Vec<Hex> = vec![hex];
+        if let (true, Some(previous)) = (increased, previous) {
+            if self.distances.contains_key(&hex) {
+                reseed = self.invalidate(hex, previous, &mut log);
+            }
+        }
+
+        for target in reseed {
+            if let Some(distance) = self.tentative(target) {
+                if improves(self.distance(target), distance) {
+                    self.assign(target, Some(distance), &mut log);
+                }
+            }
+            if let Some(distance) = self.distance(target) {
+                frontier.push(Reverse((distance, target.q(), target.r())));
+            }
+        }
+
+        self.relax(frontier, &mut log);
+
+        let mut changed: Vec<Hex> = log
+            .into_iter()
+            .filter(|(h, previous)| *previous != self.distance(*h))
+            .map(|(h, _)| h)
+            .collect();
+        changed.sort_by_key(|h| (h.q(), h.r()));
+        changed
+    }
+
+    // Updates a distance, remembering its value from before the current
+    // `set_cost` call.
+    fn assign(&mut self, hex: Hex, distance: Option<i32>, log: &mut HashMap<Hex, Option<i32>>) {
+        let previous: Option<i32> = match distance {
+            Some(distance) => self.distances.insert(hex, distance),
+            None => self.distances.remove(&hex),
+        };
+        log.entry(hex).or_insert(previous);
+    }
+
+    // Cost of stepping onto `hex` and going on from there.
+    fn through(&self, hex: Hex) -> Option<i32> {
+        Some(self.distance(hex)? + self.costs.get(&hex)?)
+    }
+
+    // Drops the distances that may rely on a path through `hex`, which cost
+    // `previous` to enter, and returns the dropped hexes.
+    fn invalidate(
+        &mut self,
+        hex: Hex,
+        previous: i32,
+        log: &mut HashMap<Hex, Option<i32>>,
+    ) -> Vec<Hex> {
+        let mut dropped: Vec<Hex> = vec![];
+        let mut pending: Vec<(Hex, i32)> = vec![(hex, self.distances[&hex] + previous)];
+        if !self.costs.contains_key(&hex) {
+            self.assign(hex, None, log);
+            dropped.push(hex);
+        }
+
+        while let Some((current, through)) = pending.pop() {
+            for direction in 0..6 {
+                let next: Hex = HexDirection::neighbor(current, direction);
+                if self.goals.contains(&next) || self.distance(next) != Some(through) {
+                    continue;
+                }
+                let cost: i32 = match self.costs.get(&next) {
+                    Some(cost) => *cost,
+                    None => continue,
+                };
+                self.assign(next, None, log);
+                dropped.push(next);
+                pending.push((next, through + cost));
+            }
+        }
+
+        dropped
+    }
+
+    fn tentative(&self, hex: Hex) -> Option<i32> {
+        self.costs.get(&hex)?;
+        if self.goals.contains(&hex) {
+            return Some(0);
+        }
+
+        (0..6)
+            .filter_map(|direction| self.through(HexDirection::neighbor(hex, direction)))
+            .min()
+    }
+
+    fn relax(
+        &mut self,
+        mut frontier: BinaryHeap<Reverse<(i32, i32, i32)>>,
+        log: &mut HashMap<Hex, Option<i32>>,
+    ) {
+        while let Some(Reverse((distance, q, r))) = frontier.pop() {
+            let hex: Hex = Hex::new(q, r);
+            if self.distance(hex) != Some(distance) {
+                continue;
+            }
+            let candidate: i32 = match self.through(hex) {
+                Some(candidate) => candidate,
+                None => continue,
+            };
+
+            for direction in 0..6 {
+                let next: Hex = HexDirection::neighbor(hex, direction);
+                if !self.costs.contains_key(&next) {
+                    continue;
+                }
+                if improves(self.distance(next), candidate) {
+                    self.assign(next, Some(candidate), log);
+                    frontier.push(Reverse((candidate, next.q(), next.r())));
+                }
+            }
+        }
+    }
+}
+
+fn improves(known: Option<i32>, candidate: i32) -> bool {
+    match known {
+        Some(known) => candidate < known,
+        None => true,
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::hexagon::{Hex, HexUtility};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Visibility {
+    Unexplored,
+    /// Seen before but not by any viewer right now.
+    Explored,
+    Visible,
+}
+
+pub struct FieldOfView;
+
+impl FieldOfView {
+    /// Hexes within `radius` of `center` whose line from the center, as
+    /// drawn by `HexUtility::line`, passes no opaque hex before reaching
+    /// them. Opaque hexes themselves can be seen, like walls.
+    pub fn visible<F>(center: Hex, radius: i32, opaque: F) -> Vec<Hex>
+    where
+        F: Fn(Hex) -> bool,
+    {
+        center
+            .range(radius)
+            .into_iter()
+            .filter(|hex| {
+                let line: Vec<Hex> = center.line(*hex);
+                line.len() < 3 || !line[1..line.len() - 1].iter().any(|step| opaque(*step))
+            })
+            .collect()
+    }
+}
+
+/// Visibility of a hex map for one player.
+///
+/// Every viewer, such as a unit or a watch tower, sees a set of hexes. A hex is
+/// visible while at least one viewer sees it and explored once any viewer has
+/// seen it. Updates only touch the hexes of the viewer that changed and report
+/// the hexes whose visibility changed.
+#[derive(Clone, Debug, Default)]
+pub struct FogOfWar {
+    viewers: HashMap<usize, Vec<Hex>>,
+    seen_by: HashMap<Hex, usize>,
+    explored: HashSet<Hex>,
+}
+
+impl FogOfWar {
+    pub fn new() -> FogOfWar {
+        FogOfWar::default()
+    }
+
+    pub fn visibility(&self, hex: Hex) -> Visibility {
+        if self.seen_by.contains_key(&hex) {
+            Visibility::Visible
+        } else if self.explored.contains(&hex) {
+            Visibility::Explored
+        } else {
+            Visibility::Unexplored
+        }
+    }
+
+    pub fn visible(&self) -> impl Iterator<Item = &Hex> {
+        self.seen_by.keys()
+    }
+
+    pub fn explored(&self) -> &HashSet<Hex> {
+        &self.explored
+    }
+
+    /// Places or moves viewer `id` to `position` seeing `radius` hexes far.
+    /// Use `|_| false` for `opaque` when nothing blocks the view. Returns the
+    /// new visibility of every hex that changed, ordered by (q, r).
+    pub fn set_viewer<F>(
+        &mut self,
+        id: usize,
+        position: Hex,
+        radius: i32,
+        opaque: F,
+    ) -> Vec<(Hex, Visibility)>
+    where
+        F: Fn(Hex) -> bool,
+    {
+        self.set_view(id, FieldOfView::visible(position, radius, opaque))
+    }
+
+    /// Like `set_viewer` with a precomputed set of hexes seen by the viewer.
+    pub fn set_view(&mut self, id: usize, hexes: Vec<Hex>) -> Vec<(Hex, Visibility)> {
+        let old: Vec<Hex> = self.viewers.remove(&id).unwrap_or_default();
+        let before: HashMap<Hex, Visibility> = old
+            .iter()
+            .chain(hexes.iter())
+            .map(|hex| (*hex, self.visibility(*hex)))
+            .collect();
+
+        for hex in old.iter() {
+            self.unsee(*hex);
+        }
+        for hex in hexes.iter() {
+            *self.seen_by.entry(*hex).or_insert(0) += 1;
+            self.explored.insert(*hex);
+        }
+        self.viewers.insert(id, hexes);
+
+        self.changes(before)
+    }
+
+    /// Removes viewer `id`, returning the hexes that are no longer visible.
+    pub fn remove_viewer(&mut self, id: usize) -> Vec<(Hex, Visibility)> {
+        let old: Vec<Hex> = self.viewers.remove(&id).unwrap_or_default();
+        let before: HashMap<Hex, Visibility> = old
+            .iter()
+            .map(|hex| (*hex, self.visibility(*hex)))
+            .collect();
+
+        for hex in old.iter() {
+            self.unsee(*hex);
+        }
+
+        self.changes(before)
+    }
+
+    fn unsee(&mut self, hex: Hex) {
+        if let Some(count) = self.seen_by.get_mut(&hex) {
+            *count -= 1;
+            if *count == 0 {
+                self.seen_by.remove(&hex);
+            }
+        }
+    }
+
+    fn changes(&self, before: HashMap<Hex, Visibility>) -> Vec<(Hex, Visibility)> {
+        let mut changed: Vec<(Hex, Visibility)> = before
+            .into_iter()
+            .filter_map(|(hex, old)| {
+                let new: Visibility = self.visibility(hex);
+                if new != old {
+                    Some((hex, new))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        changed.sort_by_key(|(hex, _)| (hex.q(), hex.r()));
+
+        changed
+    }
+}
@@ -0,0 +1,400 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use crate::hexagon::{Hex, HexUtility, OffsetCoord};
+use crate::layout::{AxisConvention, Layout, LayoutTransform, Orientation, TransformedLayout};
+use crate::point::Point;
+use crate::storage::{take, TileCodec};
+use crate::tools::{HexOffset, OffsetAxis, HEX_EVEN, HEX_ODD};
+
+pub const MAP_FILE_MAGIC: &[u8; 4] = b"HEXM";
+pub const MAP_FILE_VERSION: u16 = 1;
+/// Most tiles a `Hexagon` or `Rectangle` map file may cover.
+pub const MAP_FILE_MAX_TILES: i64 = 1 << 24;
+
+const FLAG_RLE: u8 = 1;
+
+/// Which hexes a map file covers.
+///
+/// Tiles of `Hexagon` and `Rectangle` maps are stored in a fixed order without
+/// their coordinates, `Sparse` maps store the coordinates of every tile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MapShape {
+    Sparse,
+    Hexagon {
+        center: Hex,
+        radius: i32,
+    },
+    Rectangle {
+        axis: OffsetAxis,
+        offset: i32,
+        columns: i32,
+        rows: i32,
+    },
+}
+
+impl MapShape {
+    /// Every hex of a dense shape in storage order, `None` for sparse maps.
+    pub fn hexes(&self) -> Option<Vec<Hex>> {
+        match *self {
+            MapShape::Sparse => None,
+            MapShape::Hexagon { center, radius } => Some(center.range(radius)),
+            MapShape::Rectangle {
+                axis,
+                offset,
+                columns,
+                rows,
+            } => Some(
+                (0..rows)
+                    .flat_map(|row| (0..columns).map(move |col| OffsetCoord { col, row }))
+                    .map(|coord| HexOffset::to_cube(axis, offset, coord))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// A hex map as stored on disk.
+///
+/// The file starts with `MAP_FILE_MAGIC`, the format version, the tile
+/// `schema_version` chosen by the application, a flags byte, the layout and
+/// the shape. The tile section follows, optionally run-length encoded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapFile<T> {
+    pub layout: TransformedLayout,
+    pub shape: MapShape,
+    pub schema_version: u32,
+    pub tiles: HashMap<Hex, T>,
+}
+
+impl<T: TileCodec> MapFile<T> {
+    pub fn write<W: io::Write>(&self, out: &mut W, compress: bool) -> io::Result<()> {
+        check_shape(self.shape)?;
+
+        let mut header: Vec<u8> = MAP_FILE_MAGIC.to_vec();
+        MAP_FILE_VERSION.encode(&mut header);
+        self.schema_version.encode(&mut header);
+        (if compress { FLAG_RLE } else { 0 }).encode(&mut header);
+        encode_layout(self.layout, &mut header);
+        encode_shape(self.shape, &mut header);
+
+        let mut body: Vec<u8> = vec![];
+        match self.shape.hexes() {
+            Some(hexes) => {
+                let covered: HashSet<&Hex> = hexes.iter().collect();
+                if self.tiles.keys().any(|hex| !covered.contains(hex)) {
+                    return Err(invalid("tile outside of the map shape"));
+                }
+                for hex in hexes {
+                    match self.tiles.get(&hex) {
+                        Some(tile) => {
+                            1u8.encode(&mut body);
+                            tile.encode(&mut body);
+                        }
+                        None => 0u8.encode(&mut body),
+                    }
+                }
+            }
+            None => {
+                let mut sorted: Vec<(&Hex, &T)> = self.tiles.iter().collect();
+                sorted.sort_by_key(|(hex, _)| (hex.q(), hex.r()));
+                (sorted.len() as u32).encode(&mut body);
+                for (hex, tile) in sorted {
+                    hex.q().encode(&mut body);
+                    hex.r().encode(&mut body);
+                    tile.encode(&mut body);
+                }
+            }
+        }
+
+        if compress {
+            body = rle_encode(&body);
+        }
+
+        out.write_all(&header)?;
+        out.write_all(&body)
+    }
+
+    /// Reads a file whose tiles use the current `TileCodec` encoding.
+    pub fn read<R: io::Read>(input: &mut R) -> io::Result<MapFile<T>> {
+        Self::read_with(input, |_, bytes| T::decode(bytes))
+    }
+
+    /// Reads a file decoding every tile with `decode`, which gets the schema
+    /// version stored in the file and can migrate older payloads.
+    pub fn read_with<R, F>(input: &mut R, decode: F) -> io::Result<MapFile<T>>
+    where
+        R: io::Read,
+        F: Fn(u32, &mut &[u8]) -> io::Result<T>,
+    {
+        let mut bytes: Vec<u8> = vec![];
+        input.read_to_end(&mut bytes)?;
+        let mut data: &[u8] = &bytes;
+
+        if take(&mut data, MAP_FILE_MAGIC.len())? != MAP_FILE_MAGIC {
+            return Err(invalid("not a hex map file"));
+        }
+        let version: u16 = u16::decode(&mut data)?;
+        if version != MAP_FILE_VERSION {
+            return Err(invalid("unsupported map file version"));
+        }
+        let schema_version: u32 = u32::decode(&mut data)?;
+        let flags: u8 = u8::decode(&mut data)?;
+        if flags & !FLAG_RLE != 0 {
+            return Err(invalid("unknown map file flags"));
+        }
+        let layout: TransformedLayout = decode_layout(&mut data)?;
+        let shape: MapShape = decode_shape(&mut data)?;
+        check_shape(shape)?;
+
+        let decompressed: Vec<u8>;
+        if flags & FLAG_RLE != 0 {
+            decompressed = rle_decode(data)?;
+            data = &decompressed;
+        }
+
+        let mut tiles: HashMap<Hex, T> = HashMap::new();
+        match shape.hexes() {
+            Some(hexes) => {
+                for hex in hexes {
+                    if u8::decode(&mut data)? != 0 {
+                        tiles.insert(hex, decode(schema_version, &mut data)?);
+                    }
+                }
+            }
+            None => {
+                for _ in 0..u32::decode(&mut data)? {
+                    let q: i32 = i32::decode(&mut data)?;
+                    let r: i32 = i32::decode(&mut data)?;
+                    tiles.insert(decode_hex(q, r)?, decode(schema_version, &mut data)?);
+                }
+            }
+        }
+        if !data.is_empty() {
+            return Err(invalid("data after the last tile"));
+        }
+
+        Ok(MapFile {
+            layout,
+            shape,
+            schema_version,
+            tiles,
+        })
+    }
+}
+
+/// PackBits run-length encoding: a control byte `n` below 128 is followed by
+/// `n + 1` literal bytes, a control byte `n` above 128 repeats the next byte
+/// `257 - n` times.
+pub fn rle_encode(data: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = vec![];
+    let mut i: usize = 0;
+
+    while i < data.len() {
+        let mut run: usize = 1;
+        while i + run < data.len() && run < 128 && data[i + run] == data[i] {
+            run += 1;
+        }
+
+        if run > 1 {
+            out.push((257 - run) as u8);
+            out.push(data[i]);
+            i += run;
+            continue;
+        }
+
+        let start: usize = i;
+        while i < data.len() && i - start < 128 {
+            if i + 1 < data.len() && data[i + 1] == data[i] {
+                break;
+            }
+            i += 1;
+        }
+        out.push((i - start - 1) as u8);
+        out.extend_from_slice(&data[start..i]);
+    }
+
+    out
+}
+
+pub fn rle_decode(mut data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out: Vec<u8> = vec![];
+
+    while !data.is_empty() {
+        let control: u8 = u8::decode(&mut data)?;
+        if control < 128 {
+            out.extend_from_slice(take(&mut data, control as usize + 1)?);
+        } else if control > 128 {
+            let value: u8 = u8::decode(&mut data)?;
+            out.resize(out.len() + 257 - control as usize, value);
+        }
+    }
+
+    Ok(out)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn encode_layout(transformed: TransformedLayout, out: &mut Vec<u8>) {
+    let layout: Layout = transformed.layout;
+    let o: Orientation = layout.orientation;
+    let t: LayoutTransform = transformed.transform;
+    let values: [f64; 16] = [
+        o.f0,
+        o.f1,
+        o.f2,
+        o.f3,
+        o.b0,
+        o.b1,
+        o.b2,
+        o.b3,
+        o.start_angle,
+        layout.size.x,
+        layout.size.y,
+        layout.origin.x,
+        layout.origin.y,
+        t.gap,
+        t.rotation,
+        t.tilt,
+    ];
+    for value in values.iter() {
+        value.encode(out);
+    }
+    (match t.axis {
+        AxisConvention::YDown => 0u8,
+        AxisConvention::YUp => 1u8,
+    })
+    .encode(out);
+}
+
+fn decode_layout(data: &mut &[u8]) -> io::Result<TransformedLayout> {
+    let mut v: [f64; 16] = [0.0; 16];
+    for value in v.iter_mut() {
+        *value = f64::decode(data)?;
+    }
+    let axis: AxisConvention = match u8::decode(data)? {
+        0 => AxisConvention::YDown,
+        1 => AxisConvention::YUp,
+        _ => return Err(invalid("unknown axis convention")),
+    };
+
+    let layout: Layout = Layout {
+        orientation: Orientation {
+            f0: v[0],
+            f1: v[1],
+            f2: v[2],
+            f3: v[3],
+            b0: v[4],
+            b1: v[5],
+            b2: v[6],
+            b3: v[7],
+            start_angle: v[8],
+        },
+        size: Point { x: v[9], y: v[10] },
+        origin: Point { x: v[11], y: v[12] },
+    };
+
+    Ok(layout.with_transform(LayoutTransform::new(v[13], v[14], v[15], axis)))
+}
+
+fn encode_shape(shape: MapShape, out: &mut Vec<u8>) {
+    match shape {
+        MapShape::Sparse => 0u8.encode(out),
+        MapShape::Hexagon { center, radius } => {
+            1u8.encode(out);
+            center.q().encode(out);
+            center.r().encode(out);
+            radius.encode(out);
+        }
+        MapShape::Rectangle {
+            axis,
+            offset,
+            columns,
+            rows,
+        } => {
+            2u8.encode(out);
+            (match axis {
+                OffsetAxis::Q => 0u8,
+                OffsetAxis::R => 1u8,
+            })
+            .encode(out);
+            offset.encode(out);
+            columns.encode(out);
+            rows.encode(out);
+        }
+    }
+}
+
+fn decode_shape(data: &mut &[u8]) -> io::Result<MapShape> {
+    match u8::decode(data)? {
+        0 => Ok(MapShape::Sparse),
+        1 => {
+            let q: i32 = i32::decode(data)?;
+            let r: i32 = i32::decode(data)?;
+            Ok(MapShape::Hexagon {
+                center: decode_hex(q, r)?,
+                radius: i32::decode(data)?,
+            })
+        }
+        2 => {
+            let axis: OffsetAxis = match u8::decode(data)? {
+                0 => OffsetAxis::Q,
+                1 => OffsetAxis::R,
+                _ => return Err(invalid("unknown offset axis")),
+            };
+            Ok(MapShape::Rectangle {
+                axis,
+                offset: i32::decode(data)?,
+                columns: i32::decode(data)?,
+                rows: i32::decode(data)?,
+            })
+        }
+        _ => Err(invalid("unknown map shape")),
+    }
+}
+
+// A hex whose s coordinate fits in an `i32`.
+fn decode_hex(q: i32, r: i32) -> io::Result<Hex> {
+    match q.checked_neg().and_then(|s| s.checked_sub(r)) {
+        Some(_) => Ok(Hex::new(q, r)),
+        None => Err(invalid("hex coordinates out of range")),
+    }
+}
+
+// Rejects shapes whose hexes can't be listed, in files or in memory.
+fn check_shape(shape: MapShape) -> io::Result<()> {
+    let tiles: Option<i64> = match shape {
+        MapShape::Sparse => return Ok(()),
+        MapShape::Hexagon { center, radius } => {
+            let radius: i64 = radius as i64;
+            let fits: bool = [center.q(), center.r(), center.s()]
+                .iter()
+                .all(|c| (*c as i64).abs() + radius <= i32::MAX as i64);
+            if radius < 0 || !fits {
+                return Err(invalid("hexagon radius out of range"));
+            }
+            (3 * radius).checked_mul(radius + 1).map(|tiles| tiles + 1)
+        }
+        MapShape::Rectangle {
+            offset,
+            columns,
+            rows,
+            ..
+        } => {
+            if offset != HEX_EVEN && offset != HEX_ODD {
+                return Err(invalid("offset must be even (+1) or odd (-1)"));
+            }
+            if columns < 0 || rows < 0 {
+                return Err(invalid("negative rectangle size"));
+            }
+            (columns as i64).checked_mul(rows as i64)
+        }
+    };
+
+    match tiles {
+        Some(tiles) if tiles <= MAP_FILE_MAX_TILES => Ok(()),
+        _ => Err(invalid("map shape too large")),
+    }
+}
@@ -0,0 +1,162 @@
+use crate::hexagon::{Hex, HexMath, HEX_DIRECTIONS};
+use crate::hierarchy::HexHierarchy;
+
+pub const GBT_MAX_RESOLUTION: u8 = 15;
+
+const DIGIT_BITS: u32 = 3;
+const DIGITS_BITS: u32 = DIGIT_BITS * GBT_MAX_RESOLUTION as u32;
+const BASE_BITS: u32 = 7;
+const BASE_Q_SHIFT: u32 = DIGITS_BITS;
+const BASE_R_SHIFT: u32 = DIGITS_BITS + BASE_BITS;
+const RESOLUTION_SHIFT: u32 = DIGITS_BITS + 2 * BASE_BITS;
+const BASE_MIN: i32 = -(1 << (BASE_BITS - 1));
+const BASE_MAX: i32 = (1 << (BASE_BITS - 1)) - 1;
+const UNUSED_DIGIT: u64 = 7;
+
+/// Generalized Balanced Ternary indexing with aperture 7.
+///
+/// Every cell splits into seven children: the center one (digit 0) and one per
+/// direction (digit `direction + 1`), built on `HexHierarchy` with radius 1.
+/// A hex at resolution `n` lives on the grid whose cells are `n` subdivisions
+/// below a base cell.
+///
+/// The index packs, from the high bits down: the resolution (4 bits), the base
+/// cell r and q (7 bits each, signed) and 15 digits of 3 bits, coarsest
+/// first. Digits past the resolution are set to 7.
+pub struct HexGbt;
+
+impl HexGbt {
+    /// Encodes `hex` on the grid of the given resolution. `None` when the
+    /// resolution is too fine or the base cell does not fit.
+    pub fn encode(hex: Hex, resolution: u8) -> Option<u64> {
+        if resolution > GBT_MAX_RESOLUTION {
+            return None;
+        }
+
+        let (base, locals) = HexHierarchy::split_levels(1, resolution as usize, hex);
+        if base.q() < BASE_MIN || base.q() > BASE_MAX || base.r() < BASE_MIN || base.r() > BASE_MAX
+        {
+            return None;
+        }
+
+        let mut index: u64 = (resolution as u64) << RESOLUTION_SHIFT;
+        index |= ((base.q() - BASE_MIN) as u64) << BASE_Q_SHIFT;
+        index |= ((base.r() - BASE_MIN) as u64) << BASE_R_SHIFT;
+
+        for position in 0..GBT_MAX_RESOLUTION {
+            let digit: u64 = match locals.len().checked_sub(position as usize + 1) {
+                Some(level) => digit_of(locals[level]),
+                None => UNUSED_DIGIT,
+            };
+            index |= digit << digit_shift(position);
+        }
+
+        Some(index)
+    }
+
+    /// Decodes an index into its hex and resolution. `None` when it holds an
+    /// invalid digit or a digit past the resolution other than 7.
+    pub fn decode(index: u64) -> Option<(Hex, u8)> {
+        let resolution: u8 = Self::resolution(index);
+        if resolution > GBT_MAX_RESOLUTION {
+            return None;
+        }
+        let unused: bool = (resolution..GBT_MAX_RESOLUTION)
+            .all(|position| (index >> digit_shift(position)) & mask(DIGIT_BITS) == UNUSED_DIGIT);
+        if !unused {
+            return None;
+        }
+
+        let base: Hex = Hex::new(
+            ((index >> BASE_Q_SHIFT) & mask(BASE_BITS)) as i32 + BASE_MIN,
+            ((index >> BASE_R_SHIFT) & mask(BASE_BITS)) as i32 + BASE_MIN,
+        );
+
+        let mut locals: Vec<Hex> = vec![];
+        for digit in Self::digits(index).into_iter().rev() {
+            locals.push(match digit {
+                0 => Hex::new(0, 0),
+                1..=6 => HEX_DIRECTIONS[digit as usize - 1],
+                _ => return None,
+            });
+        }
+
+        Some((HexHierarchy::join_levels(1, base, &locals), resolution))
+    }
+
+    pub fn resolution(index: u64) -> u8 {
+        (index >> RESOLUTION_SHIFT) as u8
+    }
+
+    /// Digits of the index, coarsest first, one per resolution level.
+    pub fn digits(index: u64) -> Vec<u8> {
+        let resolution: u8 = Self::resolution(index).min(GBT_MAX_RESOLUTION);
+
+        (0..resolution)
+            .map(|position| ((index >> digit_shift(position)) & mask(DIGIT_BITS)) as u8)
+            .collect()
+    }
+
+    pub fn parent(index: u64) -> Option<u64> {
+        let resolution: u8 = Self::resolution(index);
+        if resolution == 0 || resolution > GBT_MAX_RESOLUTION {
+            return None;
+        }
+
+        let last: u8 = resolution - 1;
+        let cleared: u64 = index & !(mask(DIGIT_BITS) << digit_shift(last));
+        let unused: u64 = UNUSED_DIGIT << digit_shift(last);
+        let level: u64 = (last as u64) << RESOLUTION_SHIFT;
+
+        Some(((cleared | unused) & !(mask(4) << RESOLUTION_SHIFT)) | level)
+    }
+
+    /// The seven children of the index, ordered by digit.
+    pub fn children(index: u64) -> Vec<u64> {
+        let resolution: u8 = Self::resolution(index);
+        if resolution >= GBT_MAX_RESOLUTION {
+            return vec![];
+        }
+
+        let base: u64 = (index & !(mask(4) << RESOLUTION_SHIFT))
+            | ((resolution as u64 + 1) << RESOLUTION_SHIFT);
+        let shift: u32 = digit_shift(resolution);
+
+        (0..7)
+            .map(|digit| (base & !(mask(DIGIT_BITS) << shift)) | (digit << shift))
+            .collect()
+    }
+
+    /// Adds the hexes of two indexes of the same resolution. `None` when the
+    /// resolutions differ or the sum cannot be encoded.
+    pub fn add(a: u64, b: u64) -> Option<u64> {
+        let (hex_a, resolution_a) = Self::decode(a)?;
+        let (hex_b, resolution_b) = Self::decode(b)?;
+        if resolution_a != resolution_b {
+            return None;
+        }
+
+        Self::encode(hex_a.add(hex_b), resolution_a)
+    }
+
+    pub fn neighbor(index: u64, direction: i32) -> Option<u64> {
+        let (hex, resolution) = Self::decode(index)?;
+
+        Self::encode(hex.add(HEX_DIRECTIONS[direction as usize]), resolution)
+    }
+}
+
+fn mask(bits: u32) -> u64 {
+    (1 << bits) - 1
+}
+
+fn digit_shift(position: u8) -> u32 {
+    DIGITS_BITS - DIGIT_BITS * (position as u32 + 1)
+}
+
+fn digit_of(local: Hex) -> u64 {
+    HEX_DIRECTIONS
+        .iter()
+        .position(|direction| *direction == local)
+        .map_or(0, |direction| direction as u64 + 1)
+}
@@ -0,0 +1,160 @@
+use std::collections::HashSet;
+
+use crate::hexagon::Hex;
+use crate::layout::{LayoutTool, TransformedLayout};
+use crate::point::Point;
+use crate::region::{HexRegion, OutlinePolygon};
+use crate::tiled::json_escape;
+
+/// A GeoJSON feature property value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GeoValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+pub type GeoProperties = Vec<(String, GeoValue)>;
+
+/// Maps layout pixel space to longitude and latitude, `None` keeps the pixel
+/// coordinates as they are.
+pub type Projection<'a> = Option<&'a dyn Fn(Point) -> Point>;
+
+/// Writes hexes and regions as GeoJSON feature collections.
+///
+/// Rings are closed and, after projection, follow RFC 7946: outer rings are
+/// counterclockwise and holes clockwise.
+pub struct GeoJson;
+
+impl GeoJson {
+    /// One polygon feature per hex. The properties start with the `q` and `r`
+    /// coordinates of the hex followed by the given ones.
+    pub fn hexes(
+        layout: impl Into<TransformedLayout>,
+        hexes: &[(Hex, GeoProperties)],
+        projection: Projection,
+    ) -> String {
+        let layout: TransformedLayout = layout.into();
+        let features: Vec<String> = hexes
+            .iter()
+            .map(|(hex, properties)| {
+                let ring: Vec<Point> = LayoutTool::polygon_corners(layout, *hex);
+                let mut all: GeoProperties = vec![
+                    ("q".to_string(), GeoValue::Number(hex.q() as f64)),
+                    ("r".to_string(), GeoValue::Number(hex.r() as f64)),
+                ];
+                all.extend(properties.iter().cloned());
+
+                feature("Polygon", &polygon(&ring, &[], projection), &all)
+            })
+            .collect();
+
+        collection(&features)
+    }
+
+    /// One multi-polygon feature per region, built from `HexRegion::outline`
+    /// so shared edges between hexes of a region disappear.
+    pub fn regions(
+        layout: impl Into<TransformedLayout>,
+        regions: &[(HashSet<Hex>, GeoProperties)],
+        projection: Projection,
+    ) -> String {
+        let layout: TransformedLayout = layout.into();
+        let features: Vec<String> = regions
+            .iter()
+            .map(|(hexes, properties)| {
+                let polygons: Vec<String> = HexRegion::outline(layout, hexes)
+                    .iter()
+                    .map(|outline: &OutlinePolygon| {
+                        polygon(&outline.outer, &outline.holes, projection)
+                    })
+                    .collect();
+
+                feature(
+                    "MultiPolygon",
+                    &format!("[{}]", polygons.join(",")),
+                    properties,
+                )
+            })
+            .collect();
+
+        collection(&features)
+    }
+}
+
+fn collection(features: &[String]) -> String {
+    format!(
+        "{{\"type\":\"FeatureCollection\",\"features\":[{}]}}",
+        features.join(",")
+    )
+}
+
+fn feature(kind: &str, coordinates: &str, properties: &[(String, GeoValue)]) -> String {
+    let properties: Vec<String> = properties
+        .iter()
+        .map(|(key, value)| format!("\"{}\":{}", json_escape(key), value_json(value)))
+        .collect();
+
+    format!(
+        "{{\"type\":\"Feature\",\"geometry\":{{\"type\":\"{}\",\"coordinates\":{}}},\
+         \"properties\":{{{}}}}}",
+        kind,
+        coordinates,
+        properties.join(",")
+    )
+}
+
+fn polygon(outer: &[Point], holes: &[Vec<Point>], projection: Projection) -> String {
+    let mut rings: Vec<String> = vec![ring(outer, true, projection)];
+    rings.extend(holes.iter().map(|hole| ring(hole, false, projection)));
+
+    format!("[{}]", rings.join(","))
+}
+
+fn ring(points: &[Point], outer: bool, projection: Projection) -> String {
+    let mut projected: Vec<Point> = points
+        .iter()
+        .map(|point| match projection {
+            Some(project) => project(*point),
+            None => *point,
+        })
+        .collect();
+
+    let mut area: f64 = 0.0;
+    for i in 0..projected.len() {
+        let a: Point = projected[i];
+        let b: Point = projected[(i + 1) % projected.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    if (area > 0.0) != outer {
+        projected.reverse();
+    }
+    if let Some(first) = projected.first().copied() {
+        projected.push(first);
+    }
+
+    let positions: Vec<String> = projected
+        .iter()
+        .map(|point| format!("[{},{}]", number_json(point.x), number_json(point.y)))
+        .collect();
+
+    format!("[{}]", positions.join(","))
+}
+
+fn value_json(value: &GeoValue) -> String {
+    match value {
+        GeoValue::Null => "null".to_string(),
+        GeoValue::Bool(value) => value.to_string(),
+        GeoValue::Number(value) => number_json(*value),
+        GeoValue::Text(value) => format!("\"{}\"", json_escape(value)),
+    }
+}
+
+fn number_json(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
@@ -1,5 +1,5 @@
 use float_eq::derive_float_eq;
-use std::cmp::max;
+use std::cmp::{max, min};
 use std::ops::{Neg, Sub};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
@@ -48,6 +48,16 @@ impl FractionalHex {
         return FractionalHex { q, r, s };
     }
 
+    // Largest pairwise difference of the offset from `center`; the hex cell
+    // around `center` is where this is at most 1.
+    fn spread(&self, center: Hex) -> f64 {
+        let q: f64 = self.q - center.q as f64;
+        let r: f64 = self.r - center.r as f64;
+        let s: f64 = self.s - center.s as f64;
+
+        (q - r).abs().max((r - s).abs()).max((s - q).abs())
+    }
+
     pub fn q(&self) -> f64 {
         self.q
     }
@@ -61,6 +71,16 @@ impl FractionalHex {
     }
 }
 
+impl From<Hex> for FractionalHex {
+    fn from(hex: Hex) -> FractionalHex {
+        FractionalHex {
+            q: hex.q as f64,
+            r: hex.r as f64,
+            s: hex.s as f64,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct OffsetCoord {
     pub col: i32,
@@ -132,6 +152,7 @@ pub trait HexUtility {
     fn length(&self) -> i32;
     fn distance(&self, other: Hex) -> i32;
     fn line(&self, b: Hex) -> Vec<Hex>;
+    fn range(&self, radius: i32) -> Vec<Hex>;
 }
 
 impl HexUtility for Hex {
@@ -171,11 +192,61 @@ impl HexUtility for Hex {
 
         return results;
     }
+
+    fn range(&self, radius: i32) -> Vec<Hex> {
+        let mut results: Vec<Hex> = vec![];
+
+        for q in -radius..=radius {
+            for r in max(-radius, -q - radius)..=min(radius, -q + radius) {
+                results.push(self.add(Hex::new(q, r)));
+            }
+        }
+
+        results
+    }
+}
+
+/// Distances closer than this count as a tie when rounding.
+pub const ROUND_TIE_EPSILON: f64 = 1e-9;
+
+/// How `HexRound::round_with` settles a point lying on an edge or corner
+/// shared by several hexes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TieBreak {
+    /// The hex furthest along the given `HEX_DIRECTIONS` index.
+    Direction(i32),
+    /// The hex closest to the origin.
+    Origin,
+    /// The hex with the lowest q, then the lowest r.
+    Stable,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RoundMode {
+    /// The hex containing the point, ties settled by the given policy.
+    Nearest(TieBreak),
+    /// Rounds q and r down, the corner of the axial rhombus holding the point.
+    Floor,
+    /// Rounds q and r up.
+    Ceil,
 }
 
 pub trait HexRound {
     fn round(&self) -> Hex;
+    fn round_with(&self, mode: RoundMode) -> Hex;
+    fn edge_distance(&self) -> f64;
     fn linear_interpolation(&self, other: FractionalHex, t: f64) -> FractionalHex;
+
+    /// `round` for the fractional hexes `(q[i], r[i])`, writing the hexes to
+    /// `out_q[i]` and `out_r[i]`. Vectorized where available with results
+    /// identical to `round` for coordinates within `i32`. Panics if the
+    /// slices differ in length.
+    fn round_batch(q: &[f64], r: &[f64], out_q: &mut [i32], out_r: &mut [i32])
+    where
+        Self: Sized,
+    {
+        crate::simd::round(q, r, out_q, out_r);
+    }
 }
 
 impl HexRound for FractionalHex {
@@ -203,6 +274,47 @@ impl HexRound for FractionalHex {
         };
     }
 
+    fn round_with(&self, mode: RoundMode) -> Hex {
+        let tie_break: TieBreak = match mode {
+            RoundMode::Floor => return Hex::new(self.q.floor() as i32, self.r.floor() as i32),
+            RoundMode::Ceil => return Hex::new(self.q.ceil() as i32, self.r.ceil() as i32),
+            RoundMode::Nearest(tie_break) => tie_break,
+        };
+
+        let base: Hex = self.round();
+        let mut candidates: Vec<Hex> = vec![base];
+        candidates.extend(HEX_DIRECTIONS.iter().map(|direction| base.add(*direction)));
+
+        let spread: Vec<f64> = candidates.iter().map(|c| self.spread(*c)).collect();
+        let closest: f64 = spread.iter().cloned().fold(f64::MAX, f64::min);
+        let tied = candidates
+            .iter()
+            .zip(spread.iter())
+            .filter(|(_, spread)| **spread - closest <= ROUND_TIE_EPSILON)
+            .map(|(hex, _)| *hex);
+
+        let stable = |hex: &Hex| (hex.q, hex.r);
+        match tie_break {
+            TieBreak::Direction(direction) => {
+                let toward: Hex = HEX_DIRECTIONS[direction as usize];
+                tied.min_by_key(|hex| {
+                    let along: i32 = hex.q * toward.q + hex.r * toward.r + hex.s * toward.s;
+                    (-along, stable(hex))
+                })
+            }
+            TieBreak::Origin => tied.min_by_key(|hex| (hex.length(), stable(hex))),
+            TieBreak::Stable => tied.min_by_key(stable),
+        }
+        .unwrap_or(base)
+    }
+
+    /// Distance from the point to the nearest edge of the hex containing it,
+    /// in units of the distance between neighboring hex centers. It is 0.5 at
+    /// the center and 0 on an edge.
+    fn edge_distance(&self) -> f64 {
+        ((1.0 - self.spread(self.round())) / 2.0).max(0.0)
+    }
+
     fn linear_interpolation(&self, other: FractionalHex, t: f64) -> FractionalHex {
         return FractionalHex {
             q: self.q * (1.0 - t) + other.q * t,
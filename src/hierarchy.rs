@@ -0,0 +1,79 @@
+use crate::hexagon::{FractionalHex, Hex, HexMath, HexRotation, HexRound, HexUtility};
+use crate::tools::HexDirection;
+
+/// Groups hexes into hexagonal super-hexes holding every hex within `radius`
+/// of their center.
+///
+/// Super-hex coordinates form a regular hex grid of their own, so they can be
+/// grouped again for further levels and used with the rest of the crate.
+pub struct HexHierarchy;
+
+impl HexHierarchy {
+    /// Center of the super-hex next to the origin one in direction 0. The
+    /// other directions follow by rotation.
+    pub fn basis(radius: i32) -> Hex {
+        Hex::new(2 * radius + 1, -radius)
+    }
+
+    /// Number of hexes in one super-hex.
+    pub fn area(radius: i32) -> i32 {
+        3 * radius * radius + 3 * radius + 1
+    }
+
+    pub fn center(radius: i32, super_hex: Hex) -> Hex {
+        let a: Hex = Self::basis(radius);
+        let b: Hex = a.rotate_right();
+
+        a.scale(super_hex.q()).add(b.scale(super_hex.r()))
+    }
+
+    /// Splits `hex` into the super-hex containing it and its position
+    /// relative to that super-hex center.
+    pub fn split(radius: i32, hex: Hex) -> (Hex, Hex) {
+        let a: Hex = Self::basis(radius);
+        let b: Hex = a.rotate_right();
+        let area: f64 = Self::area(radius) as f64;
+
+        let q: f64 = (b.r() * hex.q() - b.q() * hex.r()) as f64 / area;
+        let r: f64 = (a.q() * hex.r() - a.r() * hex.q()) as f64 / area;
+        let guess: Hex = FractionalHex::new(q, r).round();
+
+        // Rounding can land on a neighbor of the right super-hex near its
+        // border, the exact check settles it.
+        let super_hex: Hex = std::iter::once(guess)
+            .chain((0..6).map(|direction| HexDirection::neighbor(guess, direction)))
+            .find(|candidate| Self::center(radius, *candidate).distance(hex) <= radius)
+            .unwrap_or(guess);
+
+        (super_hex, hex.sub(Self::center(radius, super_hex)))
+    }
+
+    pub fn join(radius: i32, super_hex: Hex, local: Hex) -> Hex {
+        Self::center(radius, super_hex).add(local)
+    }
+
+    pub fn neighbor(super_hex: Hex, direction: i32) -> Hex {
+        HexDirection::neighbor(super_hex, direction)
+    }
+
+    /// Splits `hex` over `levels` nested super-hexes of the same radius.
+    /// Returns the top level super-hex and the local positions, finest first.
+    pub fn split_levels(radius: i32, levels: usize, hex: Hex) -> (Hex, Vec<Hex>) {
+        let mut current: Hex = hex;
+        let mut locals: Vec<Hex> = vec![];
+
+        for _ in 0..levels {
+            let (super_hex, local) = Self::split(radius, current);
+            locals.push(local);
+            current = super_hex;
+        }
+
+        (current, locals)
+    }
+
+    pub fn join_levels(radius: i32, top: Hex, locals: &[Hex]) -> Hex {
+        locals.iter().rev().fold(top, |super_hex, local| {
+            Self::join(radius, super_hex, *local)
+        })
+    }
+}
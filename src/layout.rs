@@ -2,6 +2,7 @@ use std::f64::consts::PI;
 
 use crate::hexagon::{Hex, FractionalHex};
 use crate::point::Point;
+use crate::simd;
 
 const SQRT_3: f64 = 1.73205080756888;
 
@@ -29,7 +30,7 @@ pub const LAYOUT_ORIENTATION_FLAT: Orientation = Orientation {
     start_angle: 0.0,
 };
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Orientation {
     pub f0: f64,
     pub f1: f64,
@@ -42,57 +43,260 @@ pub struct Orientation {
     pub start_angle: f64,
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Which way the y axis of the pixel space points.
+///
+/// The grid looks the same on screen in both conventions: `YUp` mirrors all
+/// pixel coordinates, so corner `i` stays at the same place on the tile and
+/// the corners stay counterclockwise as seen on screen. In `YUp` coordinates
+/// that is the counterclockwise winding renderers expect for front faces.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AxisConvention {
+    YDown,
+    YUp,
+}
+
+/// Placement of the grid on screen beyond size and origin.
+///
+/// Hex centers are spread apart by `gap`, then the grid is rotated by
+/// `rotation` radians and its y axis is scaled by `tilt`. Tile shapes keep
+/// their size but are rotated and tilted with the grid. Rotation is
+/// clockwise on screen for `YDown` and counterclockwise for `YUp`.
+///
+/// Build one with `new` or from `LAYOUT_TRANSFORM_NONE` with the `with_*`
+/// methods, so fields added later don't break callers, and apply it with
+/// `Layout::with_transform`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct LayoutTransform {
+    /// Pixels between the edges of neighboring tiles, exact when `size.x`
+    /// and `size.y` are equal.
+    pub gap: f64,
+    pub rotation: f64,
+    /// 1.0 is top-down, below that gives dimetric views.
+    pub tilt: f64,
+    pub axis: AxisConvention,
+}
+
+impl LayoutTransform {
+    pub const fn new(gap: f64, rotation: f64, tilt: f64, axis: AxisConvention) -> LayoutTransform {
+        LayoutTransform {
+            gap,
+            rotation,
+            tilt,
+            axis,
+        }
+    }
+
+    pub const fn with_gap(self, gap: f64) -> LayoutTransform {
+        LayoutTransform { gap, ..self }
+    }
+
+    pub const fn with_rotation(self, rotation: f64) -> LayoutTransform {
+        LayoutTransform { rotation, ..self }
+    }
+
+    pub const fn with_tilt(self, tilt: f64) -> LayoutTransform {
+        LayoutTransform { tilt, ..self }
+    }
+
+    pub const fn with_axis(self, axis: AxisConvention) -> LayoutTransform {
+        LayoutTransform { axis, ..self }
+    }
+}
+
+pub const LAYOUT_TRANSFORM_NONE: LayoutTransform = LayoutTransform {
+    gap: 0.0,
+    rotation: 0.0,
+    tilt: 1.0,
+    axis: AxisConvention::YDown,
+};
+
+/// The classic 2:1 isometric view, a diamond-shaped grid rotated by 45°.
+pub const LAYOUT_TRANSFORM_ISOMETRIC: LayoutTransform = LayoutTransform {
+    gap: 0.0,
+    rotation: PI / 4.0,
+    tilt: 0.5,
+    axis: AxisConvention::YDown,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Layout {
     pub orientation: Orientation,
     pub size: Point,
     pub origin: Point,
 }
 
-pub struct LayoutTool;
+impl Layout {
+    pub fn new(orientation: Orientation, size: Point, origin: Point) -> Layout {
+        Layout {
+            orientation,
+            size,
+            origin,
+        }
+    }
 
-impl LayoutTool {
-    pub fn hex_to_pixel(layout: Layout, hex: Hex) -> Point {
-        let orientation: Orientation = layout.orientation;
-        let size: Point = layout.size;
-        let origin: Point = layout.origin;
+    pub fn with_transform(self, transform: LayoutTransform) -> TransformedLayout {
+        TransformedLayout {
+            layout: self,
+            transform,
+        }
+    }
+}
+
+/// A layout placed on screen with a `LayoutTransform`. `LayoutTool` and the
+/// functions taking a layout accept both, a plain `Layout` being the same as
+/// one with `LAYOUT_TRANSFORM_NONE`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransformedLayout {
+    pub layout: Layout,
+    pub transform: LayoutTransform,
+}
+
+impl From<Layout> for TransformedLayout {
+    fn from(layout: Layout) -> TransformedLayout {
+        layout.with_transform(LAYOUT_TRANSFORM_NONE)
+    }
+}
+
+impl TransformedLayout {
+    // The transform factors, computed once per layout and reused per tile.
+    pub(crate) fn projection(&self) -> Projection {
+        let layout: Layout = self.layout;
+        let transform: LayoutTransform = self.transform;
+        let active: bool = transform.rotation != 0.0
+            || transform.tilt != 1.0
+            || transform.axis != AxisConvention::YDown;
+        let (sin, cos): (f64, f64) = if active {
+            transform.rotation.sin_cos()
+        } else {
+            (0.0, 1.0)
+        };
+
+        Projection {
+            orientation: layout.orientation,
+            size: layout.size,
+            origin: layout.origin,
+            // How far hex centers are spread apart along each axis to leave
+            // the gap.
+            spacing: if transform.gap == 0.0 {
+                Point { x: 1.0, y: 1.0 }
+            } else {
+                Point {
+                    x: 1.0 + transform.gap / (SQRT_3 * layout.size.x),
+                    y: 1.0 + transform.gap / (SQRT_3 * layout.size.y),
+                }
+            },
+            sin,
+            cos,
+            tilt: transform.tilt,
+            flip: transform.axis == AxisConvention::YUp,
+            active,
+        }
+    }
+}
+
+/// A layout with its transform factors precomputed. All conversions go
+/// through it, so a cached projection gives the same results as a fresh one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Projection {
+    pub(crate) orientation: Orientation,
+    pub(crate) size: Point,
+    pub(crate) origin: Point,
+    pub(crate) spacing: Point,
+    pub(crate) sin: f64,
+    pub(crate) cos: f64,
+    pub(crate) tilt: f64,
+    pub(crate) flip: bool,
+    /// False when rotation, tilt and axis leave the grid as it is.
+    pub(crate) active: bool,
+}
+
+impl Projection {
+    pub(crate) fn hex_to_pixel(&self, hex: Hex) -> Point {
+        let orientation: Orientation = self.orientation;
+        let size: Point = self.size;
 
         let x: f64 = (orientation.f0 * hex.q() as f64 + orientation.f1 * hex.r() as f64) * size.x;
         let y: f64 = (orientation.f2 * hex.q() as f64 + orientation.f3 * hex.r() as f64) * size.y;
+        let p: Point = self.project(Point {
+            x: x * self.spacing.x,
+            y: y * self.spacing.y,
+        });
 
-        return Point {
-            x: x + origin.x,
-            y: y + origin.y,
-        };
+        Point {
+            x: p.x + self.origin.x,
+            y: p.y + self.origin.y,
+        }
     }
 
-    pub fn pixel_to_hex(layout: Layout, point: Point) -> FractionalHex {
-        let orientation: Orientation = layout.orientation;
-        let size: Point = layout.size;
-        let origin: Point = layout.origin;
+    pub(crate) fn pixel_to_hex(&self, point: Point) -> FractionalHex {
+        let orientation: Orientation = self.orientation;
+        let p: Point = self.unproject(Point {
+            x: point.x - self.origin.x,
+            y: point.y - self.origin.y,
+        });
         let pt: Point = Point {
-            x: (point.x - origin.x) / size.x,
-            y: (point.y - origin.y) / size.y,
+            x: p.x / self.spacing.x / self.size.x,
+            y: p.y / self.spacing.y / self.size.y,
         };
 
         let q: f64 = orientation.b0 * pt.x + orientation.b1 * pt.y;
         let r: f64 = orientation.b2 * pt.x + orientation.b3 * pt.y;
 
-        return FractionalHex::new(q, r);
+        FractionalHex::new(q, r)
+    }
+
+    // Rotates, tilts and orients a vector of the untransformed grid.
+    pub(crate) fn project(&self, point: Point) -> Point {
+        if !self.active {
+            return point;
+        }
+        let y: f64 = (point.x * self.sin + point.y * self.cos) * self.tilt;
+
+        Point {
+            x: point.x * self.cos - point.y * self.sin,
+            y: if self.flip { -y } else { y },
+        }
+    }
+
+    fn unproject(&self, point: Point) -> Point {
+        if !self.active {
+            return point;
+        }
+        let y: f64 = if self.flip { -point.y } else { point.y } / self.tilt;
+
+        Point {
+            x: point.x * self.cos + y * self.sin,
+            y: y * self.cos - point.x * self.sin,
+        }
+    }
+}
+
+pub struct LayoutTool;
+
+impl LayoutTool {
+    pub fn hex_to_pixel(layout: impl Into<TransformedLayout>, hex: Hex) -> Point {
+        return layout.into().projection().hex_to_pixel(hex);
+    }
+
+    pub fn pixel_to_hex(layout: impl Into<TransformedLayout>, point: Point) -> FractionalHex {
+        return layout.into().projection().pixel_to_hex(point);
     }
 
-    pub fn corner_offset(layout: Layout, corner: i32) -> Point {
-        let orientation: Orientation = layout.orientation;
-        let size: Point = layout.size;
+    pub fn corner_offset(layout: impl Into<TransformedLayout>, corner: i32) -> Point {
+        let layout: TransformedLayout = layout.into();
+        let orientation: Orientation = layout.layout.orientation;
+        let size: Point = layout.layout.size;
         let angle: f64 = 2.0 * PI * (orientation.start_angle - corner as f64) / 6.0;
 
-        return Point {
+        return layout.projection().project(Point {
             x: size.x * angle.cos(),
             y: size.y * angle.sin(),
-        };
+        });
     }
 
-    pub fn polygon_corners(layout: Layout, hex: Hex) -> Vec<Point> {
+    pub fn polygon_corners(layout: impl Into<TransformedLayout>, hex: Hex) -> Vec<Point> {
+        let layout: TransformedLayout = layout.into();
         let mut corners: Vec<Point> = vec![];
         let center: Point = LayoutTool::hex_to_pixel(layout, hex);
 
@@ -106,4 +310,95 @@ impl LayoutTool {
 
         return corners;
     }
+
+    /// `hex_to_pixel` for the hexes `(q[i], r[i])`, writing the pixels to
+    /// `x[i]` and `y[i]`. Vectorized where available with results identical to
+    /// the single-hex function. Panics if the slices differ in length.
+    pub fn hex_to_pixel_batch(
+        layout: impl Into<TransformedLayout>,
+        q: &[i32],
+        r: &[i32],
+        x: &mut [f64],
+        y: &mut [f64],
+    ) {
+        simd::hex_to_pixel(layout.into(), q, r, x, y);
+    }
+
+    /// `pixel_to_hex` for the points `(x[i], y[i])`, writing the fractional
+    /// hexes to `q[i]` and `r[i]`. Vectorized where available with results
+    /// identical to the single-point function.
+    pub fn pixel_to_hex_batch(
+        layout: impl Into<TransformedLayout>,
+        x: &[f64],
+        y: &[f64],
+        q: &mut [f64],
+        r: &mut [f64],
+    ) {
+        simd::pixel_to_hex(layout.into(), x, y, q, r);
+    }
+
+    /// All six corner offsets of the layout at once.
+    pub fn corner_offsets(layout: impl Into<TransformedLayout>) -> [Point; 6] {
+        let layout: TransformedLayout = layout.into();
+        let mut offsets: [Point; 6] = [Point { x: 0.0, y: 0.0 }; 6];
+        for (i, offset) in offsets.iter_mut().enumerate() {
+            *offset = LayoutTool::corner_offset(layout, i as i32);
+        }
+
+        offsets
+    }
+}
+
+/// A layout with its transform factors and corner offsets computed once, for
+/// drawing many tiles without allocating or evaluating trigonometry per tile.
+/// Results are the same as the `LayoutTool` functions for the wrapped layout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CachedLayout {
+    layout: TransformedLayout,
+    projection: Projection,
+    offsets: [Point; 6],
+}
+
+impl CachedLayout {
+    pub fn new(layout: impl Into<TransformedLayout>) -> CachedLayout {
+        let layout: TransformedLayout = layout.into();
+        CachedLayout {
+            layout,
+            projection: layout.projection(),
+            offsets: LayoutTool::corner_offsets(layout),
+        }
+    }
+
+    pub fn layout(&self) -> TransformedLayout {
+        self.layout
+    }
+
+    pub fn corner_offsets(&self) -> [Point; 6] {
+        self.offsets
+    }
+
+    pub fn polygon_corners(&self, hex: Hex) -> [Point; 6] {
+        let center: Point = self.projection.hex_to_pixel(hex);
+        let mut corners: [Point; 6] = self.offsets;
+        for corner in corners.iter_mut() {
+            corner.x += center.x;
+            corner.y += center.y;
+        }
+
+        corners
+    }
+
+    /// Writes the six corners of every hex in order into `out`, which must
+    /// hold exactly `6 * hexes.len()` points.
+    pub fn polygon_corners_into(&self, hexes: &[Hex], out: &mut [Point]) {
+        assert_eq!(
+            hexes.len() * 6,
+            out.len(),
+            "buffer must hold six corners per hex"
+        );
+
+        for (hex, corners) in hexes.iter().zip(out.chunks_exact_mut(6)) {
+            corners.copy_from_slice(&self.polygon_corners(*hex));
+        }
+    }
 }
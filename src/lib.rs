@@ -2,6 +2,24 @@ pub mod hexagon;
 pub mod point;
 pub mod tools;
 pub mod layout;
+pub mod region;
+pub mod partition;
+pub mod flow;
+pub mod wrap;
+pub mod hierarchy;
+pub mod gbt;
+pub mod curve;
+pub mod storage;
+pub mod format;
+pub mod tiled;
+pub mod geojson;
+pub mod line;
+pub mod fixed;
+pub mod area;
+mod simd;
+pub mod pick;
+pub mod motion;
+pub mod fog;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,449 @@
+use std::cmp::max;
+use std::collections::HashSet;
+
+use crate::hexagon::{FractionalHex, Hex, HexRound, HexUtility};
+use crate::layout::{LayoutTool, TransformedLayout};
+use crate::point::Point;
+
+const TOUCH_EPSILON: f64 = 1e-9;
+
+/// Line drawing beyond `HexUtility::line`.
+///
+/// `supercover` and `thick` with an odd width are symmetric: swapping the end
+/// points gives the same set of hexes. Their results are ordered along the
+/// segment from the first point, hexes reached at the same place are ordered
+/// by (q, r).
+pub struct HexLine;
+
+impl HexLine {
+    /// The hexes the segment from `a` to `b` passes through, from the hex of
+    /// `a` to the hex of `b`, each a neighbor of the previous one.
+    ///
+    /// The segment is nudged so it never runs along an edge. Between hex
+    /// centers this gives the hexes of `HexUtility::line` in order plus the
+    /// ones whose corners the segment cuts between two of its steps. Lines
+    /// along the diagonal (1, 1, -2), where the nudge of `HexUtility::line`
+    /// leaves the side to rounding error, may pass on the other side.
+    pub fn between(a: FractionalHex, b: FractionalHex) -> Vec<Hex> {
+        let start: Hex = a.round();
+        let end: Hex = b.round();
+        if fractional_distance(a, b) == 0.0 {
+            return vec![start];
+        }
+
+        let a_nudge: FractionalHex = FractionalHex::new(a.q() + 1e-06, a.r() + 2e-06);
+        let b_nudge: FractionalHex = FractionalHex::new(b.q() + 1e-06, b.r() + 2e-06);
+        let mut crossed: Vec<(f64, Hex)> = candidates(a_nudge, b_nudge, 1)
+            .into_iter()
+            .filter_map(|hex| match overlap(a_nudge, b_nudge, hex, 1.0) {
+                Some((low, high)) if high - low > TOUCH_EPSILON => Some((low, hex)),
+                _ => None,
+            })
+            .collect();
+        sort_along(&mut crossed);
+
+        // The nudge can move an end point lying on an edge into the neighbor.
+        let mut hexes: Vec<Hex> = vec![start];
+        hexes.extend(crossed.into_iter().map(|(_, hex)| hex));
+        hexes.push(end);
+        hexes.dedup();
+
+        hexes
+    }
+
+    /// Every hex touched by the segment from `a` to `b`, including hexes it
+    /// only grazes at an edge or corner.
+    pub fn supercover(a: FractionalHex, b: FractionalHex) -> Vec<Hex> {
+        let mut touched: Vec<(f64, Hex)> = candidates(a, b, 1)
+            .into_iter()
+            .filter_map(|hex| entry(a, b, hex).map(|t| (t, hex)))
+            .collect();
+
+        sort_along(&mut touched);
+        touched.into_iter().map(|(_, hex)| hex).collect()
+    }
+
+    /// A line `width` hexes wide between `a` and `b`, including hexes around
+    /// the end points. A width of 1 gives the hexes whose center lies within
+    /// half a hex spacing of the segment between the centers, keeping both
+    /// hexes where the segment runs exactly between two.
+    ///
+    /// Every extra width adds a row of hexes one hex spacing further out,
+    /// first on the right of the segment, then on the left, alternating.
+    /// Right is seen from `a` towards `b` in a pointy layout with y down.
+    ///
+    /// Panics if `width` is less than 1.
+    pub fn thick(a: Hex, b: Hex, width: i32) -> Vec<Hex> {
+        assert!(width >= 1, "width must be at least 1");
+
+        let from: (f64, f64) = planar(FractionalHex::from(a));
+        let to: (f64, f64) = planar(FractionalHex::from(b));
+        let right: f64 = 0.5 + (width / 2) as f64;
+        let left: f64 = 0.5 + ((width - 1) / 2) as f64;
+
+        let mut covered: Vec<(f64, Hex)> = candidates(a.into(), b.into(), width)
+            .into_iter()
+            .filter_map(|hex| {
+                let point: (f64, f64) = planar(FractionalHex::from(hex));
+                let (t, distance) = project(from, to, point);
+                let side: f64 =
+                    (to.0 - from.0) * (point.1 - from.1) - (to.1 - from.1) * (point.0 - from.0);
+                let half: f64 = if side > TOUCH_EPSILON { right } else { left };
+                if distance <= half + TOUCH_EPSILON {
+                    Some((t, hex))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        sort_along(&mut covered);
+        covered.into_iter().map(|(_, hex)| hex).collect()
+    }
+
+    /// `HexUtility::line` in integer arithmetic only, giving the same hexes
+    /// on every target.
+    ///
+    /// Each step is kept as a fraction over the distance and the nudge of the
+    /// float version becomes an infinitesimal that only decides ties. The
+    /// nudge moves q and r alike, so where both are equally far from their
+    /// rounded values the float version is decided by rounding error. Those
+    /// steps replay its float operations in integers.
+    pub fn integer(a: Hex, b: Hex) -> Vec<Hex> {
+        let n: i64 = max(a.distance(b), 1) as i64;
+
+        (0..=a.distance(b) as i64)
+            .map(|i| {
+                let q: i64 = a.q() as i64 * n + (b.q() - a.q()) as i64 * i;
+                let r: i64 = a.r() as i64 * n + (b.r() - a.r()) as i64 * i;
+                let s: i64 = a.s() as i64 * n + (b.s() - a.s()) as i64 * i;
+                round_scaled(q, r, s, n, || float_q_wins(a, b, i, n))
+            })
+            .collect()
+    }
+
+    /// `between` for two pixel positions of the layout.
+    pub fn pixels(layout: impl Into<TransformedLayout>, a: Point, b: Point) -> Vec<Hex> {
+        let layout: TransformedLayout = layout.into();
+        Self::between(
+            LayoutTool::pixel_to_hex(layout, a),
+            LayoutTool::pixel_to_hex(layout, b),
+        )
+    }
+
+    /// `supercover` for two pixel positions of the layout.
+    pub fn pixels_supercover(layout: impl Into<TransformedLayout>, a: Point, b: Point) -> Vec<Hex> {
+        let layout: TransformedLayout = layout.into();
+        Self::supercover(
+            LayoutTool::pixel_to_hex(layout, a),
+            LayoutTool::pixel_to_hex(layout, b),
+        )
+    }
+}
+
+fn fractional_distance(a: FractionalHex, b: FractionalHex) -> f64 {
+    ((a.q() - b.q()).abs() + (a.r() - b.r()).abs() + (a.s() - b.s()).abs()) / 2.0
+}
+
+// Rounds (q, r, s) / n nudged by (+e, +e, -2e) like `HexUtility::line`.
+// `q_wins` settles the tie between q and r the nudge leaves open.
+fn round_scaled<F>(q: i64, r: i64, s: i64, n: i64, q_wins: F) -> Hex
+where
+    F: FnOnce() -> bool,
+{
+    let qi: i64 = (2 * q + n).div_euclid(2 * n);
+    let ri: i64 = (2 * r + n).div_euclid(2 * n);
+    let si: i64 = -(-2 * s + n).div_euclid(2 * n);
+
+    // Rounding error as (size, coefficient of the nudge), compared in order.
+    let error = |rounded: i64, value: i64, nudge: i64| -> (i64, i64) {
+        let d: i64 = rounded * n - value;
+        if d > 0 {
+            (d, -nudge)
+        } else if d < 0 {
+            (-d, nudge)
+        } else {
+            (0, nudge.abs())
+        }
+    };
+    let q_diff: (i64, i64) = error(qi, q, 1);
+    let r_diff: (i64, i64) = error(ri, r, 1);
+    let s_diff: (i64, i64) = error(si, s, -2);
+
+    let q_over_r: bool = q_diff > r_diff || (q_diff == r_diff && q_diff > s_diff && q_wins());
+    if q_over_r && q_diff > s_diff {
+        Hex::new((-ri - si) as i32, ri as i32)
+    } else if r_diff > s_diff {
+        Hex::new(qi as i32, (-qi - si) as i32)
+    } else {
+        Hex::new(qi as i32, ri as i32)
+    }
+}
+
+// Whether `HexUtility::line` from `a` to `b` finds q further from its rounded
+// value than r at step `i` of `n`, replaying its float operations.
+fn float_q_wins(a: Hex, b: Hex, i: i64, n: i64) -> bool {
+    let one: SoftFloat = SoftFloat::from_int(1);
+    let nudge: SoftFloat = SoftFloat::from_bits(NUDGE_BITS);
+    let t: SoftFloat = one.div(SoftFloat::from_int(n)).mul(SoftFloat::from_int(i));
+    let rest: SoftFloat = one.add(t.negate());
+
+    let diff = |from: i32, to: i32| -> SoftFloat {
+        let from: SoftFloat = SoftFloat::from_int(from as i64).add(nudge);
+        let to: SoftFloat = SoftFloat::from_int(to as i64).add(nudge);
+        let value: SoftFloat = from.mul(rest).add(to.mul(t));
+        SoftFloat::from_int(value.round()).add(value.negate()).abs()
+    };
+
+    diff(a.q(), b.q()).greater(diff(a.r(), b.r()))
+}
+
+// The bits of the `1e-06` nudge.
+const NUDGE_BITS: u64 = 0x3eb0_c6f7_a0b5_ed8d;
+
+// An `f64` emulated with integers, as far as `float_q_wins` needs it: normal
+// numbers only, rounded to nearest with ties to even like IEEE 754. The value
+// is `m * 2^e` with `m` normalized to 53 bits, or zero when `m` is 0.
+#[derive(Clone, Copy, Debug)]
+struct SoftFloat {
+    negative: bool,
+    m: u64,
+    e: i32,
+}
+
+impl SoftFloat {
+    fn from_bits(bits: u64) -> SoftFloat {
+        let exponent: i32 = ((bits >> 52) & 0x7ff) as i32;
+        let fraction: u64 = bits & ((1 << 52) - 1);
+        SoftFloat::new(
+            bits >> 63 == 1,
+            (fraction | 1 << 52) as u128,
+            exponent - 1075,
+            false,
+        )
+    }
+
+    fn from_int(value: i64) -> SoftFloat {
+        SoftFloat::new(value < 0, value.unsigned_abs() as u128, 0, false)
+    }
+
+    // Rounds `m * 2^e` to 53 bits. `sticky` tells that the exact value is a
+    // bit more than that.
+    fn new(negative: bool, mut m: u128, mut e: i32, sticky: bool) -> SoftFloat {
+        if m == 0 {
+            return SoftFloat {
+                negative,
+                m: 0,
+                e: 0,
+            };
+        }
+
+        let bits: i32 = 128 - m.leading_zeros() as i32;
+        if bits > 53 {
+            let shift: i32 = bits - 53;
+            let rest: u128 = m & ((1 << shift) - 1);
+            let half: u128 = 1 << (shift - 1);
+            m >>= shift;
+            e += shift;
+            if rest > half || (rest == half && (sticky || m & 1 == 1)) {
+                m += 1;
+                if m == 1 << 53 {
+                    m >>= 1;
+                    e += 1;
+                }
+            }
+        } else {
+            m <<= 53 - bits;
+            e -= 53 - bits;
+        }
+
+        SoftFloat {
+            negative,
+            m: m as u64,
+            e,
+        }
+    }
+
+    fn negate(self) -> SoftFloat {
+        SoftFloat {
+            negative: !self.negative,
+            ..self
+        }
+    }
+
+    fn abs(self) -> SoftFloat {
+        SoftFloat {
+            negative: false,
+            ..self
+        }
+    }
+
+    fn add(self, other: SoftFloat) -> SoftFloat {
+        if self.m == 0 {
+            return other;
+        }
+        if other.m == 0 {
+            return self;
+        }
+
+        let (big, small) = if self.e >= other.e {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let shift: i32 = big.e - small.e;
+        // Far below the last bit of `big`, `small` only decides which way a
+        // value between two neighbors rounds, so a sixteenth of a bit stands
+        // in for it.
+        let (big_m, small_m, e): (i128, i128, i32) = if shift <= 64 {
+            ((big.m as i128) << shift, small.m as i128, small.e)
+        } else {
+            ((big.m as i128) << 4, 1, big.e - 4)
+        };
+
+        let signed = |negative: bool, m: i128| -> i128 {
+            if negative {
+                -m
+            } else {
+                m
+            }
+        };
+        let sum: i128 = signed(big.negative, big_m) + signed(small.negative, small_m);
+        SoftFloat::new(sum < 0, sum.unsigned_abs(), e, false)
+    }
+
+    fn mul(self, other: SoftFloat) -> SoftFloat {
+        SoftFloat::new(
+            self.negative != other.negative,
+            self.m as u128 * other.m as u128,
+            self.e + other.e,
+            false,
+        )
+    }
+
+    fn div(self, other: SoftFloat) -> SoftFloat {
+        let numerator: u128 = (self.m as u128) << 74;
+        SoftFloat::new(
+            self.negative != other.negative,
+            numerator / other.m as u128,
+            self.e - 74 - other.e,
+            !numerator.is_multiple_of(other.m as u128),
+        )
+    }
+
+    // `f64::round`, halfway values away from zero.
+    fn round(self) -> i64 {
+        let magnitude: i64 = if self.e >= 0 {
+            (self.m as i64) << self.e
+        } else if self.e < -64 {
+            0
+        } else {
+            ((self.m as u128 + (1 << (-self.e - 1))) >> -self.e) as i64
+        };
+
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    // Compares magnitudes.
+    fn greater(self, other: SoftFloat) -> bool {
+        if self.m == 0 || other.m == 0 {
+            return self.m > other.m;
+        }
+        (self.e, self.m) > (other.e, other.m)
+    }
+}
+
+// Hexes near the segment: the ones under dense samples along it and
+// everything within `margin` of those.
+fn candidates(a: FractionalHex, b: FractionalHex, margin: i32) -> Vec<Hex> {
+    let samples: i32 = 2 * fractional_distance(a, b).ceil() as i32 + 1;
+    let mut seen: HashSet<Hex> = HashSet::new();
+    let mut hexes: Vec<Hex> = vec![];
+
+    for i in 0..=samples {
+        let center: Hex = a.linear_interpolation(b, i as f64 / samples as f64).round();
+        for hex in center.range(margin) {
+            if seen.insert(hex) {
+                hexes.push(hex);
+            }
+        }
+    }
+
+    hexes
+}
+
+// Where along the segment it first touches the cell of `hex`, if at all.
+fn entry(a: FractionalHex, b: FractionalHex, hex: Hex) -> Option<f64> {
+    overlap(a, b, hex, 1.0 + TOUCH_EPSILON).map(|(low, _)| low)
+}
+
+// The part of the segment, from 0 to 1, inside the cell of `hex`. The cell is
+// where the pairwise differences of the offset stay within `limit`.
+fn overlap(a: FractionalHex, b: FractionalHex, hex: Hex, limit: f64) -> Option<(f64, f64)> {
+    let offset = |p: FractionalHex| -> [f64; 3] {
+        let q: f64 = p.q() - hex.q() as f64;
+        let r: f64 = p.r() - hex.r() as f64;
+        let s: f64 = p.s() - hex.s() as f64;
+        [q - r, r - s, s - q]
+    };
+    let start: [f64; 3] = offset(a);
+    let end: [f64; 3] = offset(b);
+
+    let mut low: f64 = 0.0;
+    let mut high: f64 = 1.0;
+    for i in 0..3 {
+        let delta: f64 = end[i] - start[i];
+        if delta == 0.0 {
+            if start[i].abs() > limit {
+                return None;
+            }
+            continue;
+        }
+        let t0: f64 = (-limit - start[i]) / delta;
+        let t1: f64 = (limit - start[i]) / delta;
+        low = low.max(t0.min(t1));
+        high = high.min(t0.max(t1));
+    }
+
+    if low <= high {
+        Some((low, high))
+    } else {
+        None
+    }
+}
+
+// Cube coordinates on a plane where neighboring centers are 1 apart.
+fn planar(hex: FractionalHex) -> (f64, f64) {
+    (hex.q() + hex.r() / 2.0, hex.r() * 3f64.sqrt() / 2.0)
+}
+
+// Position of `point` along the segment (0 to 1) and its distance to it.
+fn project(from: (f64, f64), to: (f64, f64), point: (f64, f64)) -> (f64, f64) {
+    let dx: f64 = to.0 - from.0;
+    let dy: f64 = to.1 - from.1;
+    let length: f64 = dx * dx + dy * dy;
+
+    let t: f64 = if length == 0.0 {
+        0.0
+    } else {
+        (((point.0 - from.0) * dx + (point.1 - from.1) * dy) / length).clamp(0.0, 1.0)
+    };
+    let x: f64 = from.0 + t * dx - point.0;
+    let y: f64 = from.1 + t * dy - point.1;
+
+    (t, (x * x + y * y).sqrt())
+}
+
+fn sort_along(hexes: &mut [(f64, Hex)]) {
+    hexes.sort_by(|(ta, a), (tb, b)| {
+        let ta: f64 = (ta / TOUCH_EPSILON).round();
+        let tb: f64 = (tb / TOUCH_EPSILON).round();
+        ta.partial_cmp(&tb)
+            .unwrap()
+            .then((a.q(), a.r()).cmp(&(b.q(), b.r())))
+    });
+}
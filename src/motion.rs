@@ -0,0 +1,171 @@
+use crate::hexagon::Hex;
+use crate::layout::{LayoutTool, TransformedLayout};
+use crate::point::Point;
+
+/// Maps the progress along a path, from 0 to 1, to the eased progress.
+#[derive(Clone, Copy, Debug)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    Custom(fn(f64) -> f64),
+}
+
+impl Easing {
+    pub fn apply(&self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Easing::Custom(curve) => curve(t),
+        }
+    }
+}
+
+/// Where a follower is at some time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathSample {
+    pub position: Point,
+    /// Direction of travel in radians in pixel space.
+    pub facing: f64,
+    pub finished: bool,
+}
+
+/// Moves along a hex path through the tile centers of a layout.
+///
+/// The speed is in pixels per time unit along the straight path between the
+/// centers and the easing applies to the whole path. With smoothing the
+/// position follows a Catmull-Rom spline through the centers instead, still
+/// reaching each center at the same time as without it.
+#[derive(Clone, Debug)]
+pub struct PathFollower {
+    points: Vec<Point>,
+    distances: Vec<f64>,
+    speed: f64,
+    easing: Easing,
+    smooth: bool,
+}
+
+impl PathFollower {
+    /// Panics if `path` is empty or `speed` is not a positive finite number.
+    pub fn new(
+        layout: impl Into<TransformedLayout>,
+        path: &[Hex],
+        speed: f64,
+        easing: Easing,
+        smooth: bool,
+    ) -> PathFollower {
+        assert!(!path.is_empty(), "path must contain at least one hex");
+        assert!(
+            speed > 0.0 && speed.is_finite(),
+            "speed must be positive and finite"
+        );
+
+        let layout: TransformedLayout = layout.into();
+        let points: Vec<Point> = path
+            .iter()
+            .map(|hex| LayoutTool::hex_to_pixel(layout, *hex))
+            .collect();
+        let mut distances: Vec<f64> = vec![0.0];
+        for pair in points.windows(2) {
+            let step: f64 = (pair[1].x - pair[0].x).hypot(pair[1].y - pair[0].y);
+            distances.push(distances[distances.len() - 1] + step);
+        }
+
+        PathFollower {
+            points,
+            distances,
+            speed,
+            easing,
+            smooth,
+        }
+    }
+
+    pub fn length(&self) -> f64 {
+        self.distances[self.distances.len() - 1]
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.length() / self.speed
+    }
+
+    pub fn sample(&self, time: f64) -> PathSample {
+        let duration: f64 = self.duration();
+        let progress: f64 = if duration > 0.0 {
+            (time / duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let travelled: f64 = self.easing.apply(progress) * self.length();
+
+        // The segment holding the travelled distance, zero-length steps skipped.
+        let last: usize = self.points.len() - 1;
+        let segment: usize = (0..last)
+            .find(|i| {
+                self.distances[i + 1] >= travelled && self.distances[i + 1] > self.distances[*i]
+            })
+            .unwrap_or(last.saturating_sub(1));
+        let (position, tangent): (Point, Point) = if last == 0 {
+            (self.points[0], Point { x: 0.0, y: 0.0 })
+        } else {
+            let span: f64 = self.distances[segment + 1] - self.distances[segment];
+            let t: f64 = if span > 0.0 {
+                ((travelled - self.distances[segment]) / span).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+            self.interpolate(segment, t)
+        };
+
+        PathSample {
+            position,
+            facing: if tangent.x == 0.0 && tangent.y == 0.0 {
+                0.0
+            } else {
+                tangent.y.atan2(tangent.x)
+            },
+            finished: progress >= 1.0,
+        }
+    }
+
+    // Position and tangent at `t` between point `i` and point `i + 1`.
+    fn interpolate(&self, i: usize, t: f64) -> (Point, Point) {
+        let p1: Point = self.points[i];
+        let p2: Point = self.points[i + 1];
+        if !self.smooth {
+            let tangent: Point = Point {
+                x: p2.x - p1.x,
+                y: p2.y - p1.y,
+            };
+            return (
+                Point {
+                    x: p1.x + tangent.x * t,
+                    y: p1.y + tangent.y * t,
+                },
+                tangent,
+            );
+        }
+
+        // The end points are repeated so the spline starts and stops on them.
+        let p0: Point = self.points[i.saturating_sub(1)];
+        let p3: Point = self.points[(i + 2).min(self.points.len() - 1)];
+        let spline = |a: f64, b: f64, c: f64, d: f64| -> (f64, f64) {
+            let value: f64 = 0.5
+                * (2.0 * b
+                    + (c - a) * t
+                    + (2.0 * a - 5.0 * b + 4.0 * c - d) * t * t
+                    + (3.0 * b - a - 3.0 * c + d) * t * t * t);
+            let slope: f64 = 0.5
+                * ((c - a)
+                    + 2.0 * (2.0 * a - 5.0 * b + 4.0 * c - d) * t
+                    + 3.0 * (3.0 * b - a - 3.0 * c + d) * t * t);
+            (value, slope)
+        };
+        let (x, dx) = spline(p0.x, p1.x, p2.x, p3.x);
+        let (y, dy) = spline(p0.y, p1.y, p2.y, p3.y);
+
+        (Point { x, y }, Point { x: dx, y: dy })
+    }
+}
@@ -0,0 +1,82 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::hexagon::Hex;
+use crate::tools::HexDirection;
+
+/// The owning seed of a hex and the cost of reaching it from that seed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PartitionCell {
+    pub seed: usize,
+    pub distance: i32,
+}
+
+pub struct HexPartition;
+
+impl HexPartition {
+    /// Splits `hexes` into the regions closest to each of `seeds`, moving one
+    /// step at a time between neighbors of the set.
+    ///
+    /// When the set is convex, such as a filled hexagon, the distance matches
+    /// `HexUtility::distance` to the owning seed. Hexes at the same distance
+    /// from several seeds go to the seed with the lowest index.
+    pub fn nearest(seeds: &[Hex], hexes: &HashSet<Hex>) -> HashMap<Hex, PartitionCell> {
+        Self::weighted(
+            seeds,
+            |hex| if hexes.contains(&hex) { Some(1) } else { None },
+        )
+    }
+
+    /// Splits the map into the regions cheapest to reach from each of `seeds`.
+    ///
+    /// `cost` returns the price of entering a hex, or `None` for hexes that
+    /// are blocked or off the map; it must return `None` outside a finite
+    /// area, otherwise the search never ends. Seeds on blocked hexes are
+    /// skipped and ties go to the seed with the lowest index.
+    pub fn weighted<F>(seeds: &[Hex], cost: F) -> HashMap<Hex, PartitionCell>
+    where
+        F: Fn(Hex) -> Option<i32>,
+    {
+        let mut best: HashMap<Hex, (i32, usize)> = HashMap::new();
+        let mut cells: HashMap<Hex, PartitionCell> = HashMap::new();
+        let mut frontier: BinaryHeap<Reverse<(i32, usize, i32, i32)>> = BinaryHeap::new();
+
+        for (seed, hex) in seeds.iter().enumerate() {
+            if cost(*hex).is_none() || best.contains_key(hex) {
+                continue;
+            }
+            best.insert(*hex, (0, seed));
+            frontier.push(Reverse((0, seed, hex.q(), hex.r())));
+        }
+
+        while let Some(Reverse((distance, seed, q, r))) = frontier.pop() {
+            let hex: Hex = Hex::new(q, r);
+            if cells.contains_key(&hex) {
+                continue;
+            }
+            cells.insert(hex, PartitionCell { seed, distance });
+
+            for direction in 0..6 {
+                let next: Hex = HexDirection::neighbor(hex, direction);
+                if cells.contains_key(&next) {
+                    continue;
+                }
+                let step: i32 = match cost(next) {
+                    Some(step) => step,
+                    None => continue,
+                };
+                let candidate: (i32, usize) = (distance + step, seed);
+                let improves: bool = match best.get(&next) {
+                    Some(known) => candidate < *known,
+                    None => true,
+                };
+                if improves {
+                    best.insert(next, candidate);
+                    frontier.push(Reverse((candidate.0, seed, next.q(), next.r())));
+                }
+            }
+        }
+
+        cells
+    }
+}
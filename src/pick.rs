@@ -0,0 +1,92 @@
+use crate::hexagon::{Hex, HexRound};
+use crate::layout::{LayoutTool, TransformedLayout};
+use crate::point::Point;
+
+/// What a picked point is closest to within the tolerance.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PickTarget {
+    Center,
+    /// The edge from corner `i` to corner `i + 1`, see
+    /// `HexRegion::edge_directions` for the neighbor across it.
+    Edge(i32),
+    Corner(i32),
+}
+
+/// The result of picking a pixel position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pick {
+    pub hex: Hex,
+    pub target: PickTarget,
+    /// Nearest edge of `hex` and the distance to it in pixels.
+    pub edge: i32,
+    pub edge_distance: f64,
+    /// Nearest corner of `hex` and the distance to it in pixels.
+    pub corner: i32,
+    pub corner_distance: f64,
+}
+
+pub struct HexPicker;
+
+impl HexPicker {
+    /// Picks the hex under `point`. The target is a corner if one is within
+    /// `tolerance` pixels, otherwise an edge within `tolerance`, otherwise the
+    /// center. Corners win over edges since they lie on two edges at once.
+    pub fn pick(layout: impl Into<TransformedLayout>, point: Point, tolerance: f64) -> Pick {
+        let layout: TransformedLayout = layout.into();
+        let hex: Hex = LayoutTool::pixel_to_hex(layout, point).round();
+        let corners: Vec<Point> = LayoutTool::polygon_corners(layout, hex);
+
+        let mut corner: usize = 0;
+        let mut corner_distance: f64 = f64::MAX;
+        let mut edge: usize = 0;
+        let mut edge_distance: f64 = f64::MAX;
+        for i in 0..6 {
+            let to_corner: f64 = distance(point, corners[i]);
+            if to_corner < corner_distance {
+                corner = i;
+                corner_distance = to_corner;
+            }
+            let to_edge: f64 = segment_distance(point, corners[i], corners[(i + 1) % 6]);
+            if to_edge < edge_distance {
+                edge = i;
+                edge_distance = to_edge;
+            }
+        }
+
+        let target: PickTarget = if corner_distance <= tolerance {
+            PickTarget::Corner(corner as i32)
+        } else if edge_distance <= tolerance {
+            PickTarget::Edge(edge as i32)
+        } else {
+            PickTarget::Center
+        };
+
+        Pick {
+            hex,
+            target,
+            edge: edge as i32,
+            edge_distance,
+            corner: corner as i32,
+            corner_distance,
+        }
+    }
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    ((a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y)).sqrt()
+}
+
+fn segment_distance(point: Point, a: Point, b: Point) -> f64 {
+    let dx: f64 = b.x - a.x;
+    let dy: f64 = b.y - a.y;
+    let t: f64 =
+        (((point.x - a.x) * dx + (point.y - a.y) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+
+    distance(
+        point,
+        Point {
+            x: a.x + t * dx,
+            y: a.y + t * dy,
+        },
+    )
+}
@@ -0,0 +1,305 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::hexagon::{Hex, HexMath, HexUtility};
+use crate::layout::{LayoutTool, TransformedLayout};
+use crate::point::Point;
+use crate::tools::HexDirection;
+
+/// A closed boundary polygon of a set of hexes.
+///
+/// `outer` follows the same winding as `LayoutTool::polygon_corners`,
+/// every ring in `holes` runs the opposite way.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlinePolygon {
+    pub outer: Vec<Point>,
+    pub holes: Vec<Vec<Point>>,
+}
+
+pub struct HexRegion;
+
+impl HexRegion {
+    /// Maps every polygon edge (corner `i` to corner `i + 1`) to the direction
+    /// of the neighbor sharing that edge for the given layout.
+    pub fn edge_directions(layout: impl Into<TransformedLayout>) -> [i32; 6] {
+        let layout: TransformedLayout = layout.into();
+        let origin: Point = LayoutTool::hex_to_pixel(layout, Hex::new(0, 0));
+        let mut directions: [i32; 6] = [0; 6];
+
+        for (edge, direction) in directions.iter_mut().enumerate() {
+            let a: Point = LayoutTool::corner_offset(layout, edge as i32);
+            let b: Point = LayoutTool::corner_offset(layout, edge as i32 + 1);
+            let mid: Point = Point {
+                x: (a.x + b.x) / 2.0,
+                y: (a.y + b.y) / 2.0,
+            };
+
+            let mut best: f64 = f64::MAX;
+            for d in 0..6 {
+                let center: Point = LayoutTool::hex_to_pixel(layout, HexDirection::direction(d));
+                let dx: f64 = (center.x - origin.x) / 2.0 - mid.x;
+                let dy: f64 = (center.y - origin.y) / 2.0 - mid.y;
+                let error: f64 = dx * dx + dy * dy;
+                if error < best {
+                    best = error;
+                    *direction = d;
+                }
+            }
+        }
+
+        directions
+    }
+
+    /// Returns the outline of the union of `hexes` as closed polygons.
+    ///
+    /// Edges shared by two hexes of the set are dropped, the remaining ones are
+    /// chained into rings. Each ring starts at its lowest vertex and polygons
+    /// are returned in a stable order, so the result does not depend on the
+    /// iteration order of the set.
+    pub fn outline(
+        layout: impl Into<TransformedLayout>,
+        hexes: &HashSet<Hex>,
+    ) -> Vec<OutlinePolygon> {
+        let layout: TransformedLayout = layout.into();
+        let directions: [i32; 6] = Self::edge_directions(layout);
+
+        let mut sorted: Vec<Hex> = hexes.iter().copied().collect();
+        sorted.sort_by_key(|h| (h.q(), h.r()));
+
+        // A vertex is shared by three hexes, the sum of their coordinates
+        // identifies it exactly without comparing floats.
+        let vertex_key = |hex: Hex, corner: usize| -> (i32, i32) {
+            let before: Hex = HexDirection::neighbor(hex, directions[(corner + 5) % 6]);
+            let after: Hex = HexDirection::neighbor(hex, directions[corner % 6]);
+            let sum: Hex = hex.add(before).add(after);
+            (sum.q(), sum.r())
+        };
+
+        let mut edges: HashMap<(i32, i32), ((i32, i32), Point)> = HashMap::new();
+        for hex in sorted.iter() {
+            let center: Point = LayoutTool::hex_to_pixel(layout, *hex);
+            for (corner, direction) in directions.iter().enumerate() {
+                if hexes.contains(&HexDirection::neighbor(*hex, *direction)) {
+                    continue;
+                }
+                let offset: Point = LayoutTool::corner_offset(layout, corner as i32);
+                let point: Point = Point {
+                    x: center.x + offset.x,
+                    y: center.y + offset.y,
+                };
+                edges.insert(
+                    vertex_key(*hex, corner),
+                    (vertex_key(*hex, corner + 1), point),
+                );
+            }
+        }
+
+        let mut starts: Vec<(i32, i32)> = edges.keys().copied().collect();
+        starts.sort_unstable();
+
+        let mut rings: Vec<Vec<Point>> = vec![];
+        let mut visited: HashSet<(i32, i32)> = HashSet::new();
+        for start in starts {
+            if visited.contains(&start) {
+                continue;
+            }
+            let mut ring: Vec<Point> = vec![];
+            let mut key: (i32, i32) = start;
+            while visited.insert(key) {
+                let (next, point) = edges[&key];
+                ring.push(point);
+                key = next;
+            }
+            rings.push(ring);
+        }
+
+        let reference: f64 = signed_area(&LayoutTool::polygon_corners(layout, Hex::new(0, 0)));
+        let (outers, holes): (Vec<Vec<Point>>, Vec<Vec<Point>>) = rings
+            .into_iter()
+            .partition(|ring| signed_area(ring) * reference > 0.0);
+
+        let mut polygons: Vec<OutlinePolygon> = outers
+            .into_iter()
+            .map(|outer| OutlinePolygon {
+                outer,
+                holes: vec![],
+            })
+            .collect();
+
+        // A hole belongs to the smallest outer ring that contains it.
+        for hole in holes {
+            let mut owner: Option<usize> = None;
+            let mut owner_area: f64 = f64::MAX;
+            for (i, polygon) in polygons.iter().enumerate() {
+                let area: f64 = signed_area(&polygon.outer).abs();
+                if area < owner_area && contains_point(&polygon.outer, hole[0]) {
+                    owner = Some(i);
+                    owner_area = area;
+                }
+            }
+            if let Some(i) = owner {
+                polygons[i].holes.push(hole);
+            }
+        }
+
+        polygons
+    }
+
+    /// Collects every hex reachable from `seed` through hexes matching
+    /// `predicate`, without going further than `bound` steps from the seed.
+    /// Returns an empty set when the seed itself does not match.
+    pub fn flood_fill<F>(seed: Hex, bound: i32, predicate: F) -> HashSet<Hex>
+    where
+        F: Fn(Hex) -> bool,
+    {
+        let mut filled: HashSet<Hex> = HashSet::new();
+        if !predicate(seed) {
+            return filled;
+        }
+
+        let mut frontier: VecDeque<Hex> = VecDeque::new();
+        filled.insert(seed);
+        frontier.push_back(seed);
+
+        while let Some(hex) = frontier.pop_front() {
+            for direction in 0..6 {
+                let next: Hex = HexDirection::neighbor(hex, direction);
+                if seed.distance(next) > bound || filled.contains(&next) || !predicate(next) {
+                    continue;
+                }
+                filled.insert(next);
+                frontier.push_back(next);
+            }
+        }
+
+        filled
+    }
+
+    /// Labels the connected groups of hexes matching `predicate` inside the
+    /// hexagonal area of `radius` around `center`.
+    ///
+    /// Labels start at zero and are handed out in scanning order of
+    /// `HexUtility::range`, hexes that do not match get no label.
+    pub fn label_components<F>(center: Hex, radius: i32, predicate: F) -> HashMap<Hex, usize>
+    where
+        F: Fn(Hex) -> bool,
+    {
+        label(&center.range(radius), |hex| {
+            center.distance(hex) <= radius && predicate(hex)
+        })
+    }
+
+    /// Splits `hexes` into its connected groups, ordered by their first hex in
+    /// (q, r) order.
+    pub fn components(hexes: &HashSet<Hex>) -> Vec<HashSet<Hex>> {
+        let mut sorted: Vec<Hex> = hexes.iter().copied().collect();
+        sorted.sort_by_key(|h| (h.q(), h.r()));
+
+        group(label(&sorted, |hex| hexes.contains(&hex)))
+    }
+
+    /// Finds the areas inside the hexagonal area of `radius` around `center`
+    /// that do not match `predicate` and are fully enclosed by hexes that do.
+    /// Anything connected to the edge of the bound counts as outside.
+    pub fn enclosed<F>(center: Hex, radius: i32, predicate: F) -> Vec<HashSet<Hex>>
+    where
+        F: Fn(Hex) -> bool,
+    {
+        let labels: HashMap<Hex, usize> =
+            Self::label_components(center, radius, |hex| !predicate(hex));
+
+        let mut open: HashSet<usize> = HashSet::new();
+        for (hex, id) in labels.iter() {
+            if center.distance(*hex) == radius {
+                open.insert(*id);
+            }
+        }
+
+        group(labels)
+            .into_iter()
+            .enumerate()
+            .filter(|(id, _)| !open.contains(id))
+            .map(|(_, component)| component)
+            .collect()
+    }
+
+    /// Finds the holes of `hexes`: groups of missing hexes completely
+    /// surrounded by the set, ordered by their lowest (q, r) hex.
+    pub fn holes(hexes: &HashSet<Hex>) -> Vec<HashSet<Hex>> {
+        let center: Hex = match hexes.iter().min_by_key(|hex| (hex.q(), hex.r())) {
+            Some(hex) => *hex,
+            None => return vec![],
+        };
+        let radius: i32 = hexes.iter().map(|h| center.distance(*h)).max().unwrap_or(0) + 1;
+
+        let mut holes: Vec<HashSet<Hex>> =
+            Self::enclosed(center, radius, |hex| hexes.contains(&hex));
+        holes.sort_by_key(|hole| hole.iter().map(|hex| (hex.q(), hex.r())).min());
+        holes
+    }
+}
+
+fn label<F>(candidates: &[Hex], predicate: F) -> HashMap<Hex, usize>
+where
+    F: Fn(Hex) -> bool,
+{
+    let mut labels: HashMap<Hex, usize> = HashMap::new();
+    let mut next_label: usize = 0;
+
+    for start in candidates.iter() {
+        if labels.contains_key(start) || !predicate(*start) {
+            continue;
+        }
+
+        let mut frontier: Vec<Hex> = vec![*start];
+        labels.insert(*start, next_label);
+        while let Some(hex) = frontier.pop() {
+            for direction in 0..6 {
+                let next: Hex = HexDirection::neighbor(hex, direction);
+                if labels.contains_key(&next) || !predicate(next) {
+                    continue;
+                }
+                labels.insert(next, next_label);
+                frontier.push(next);
+            }
+        }
+
+        next_label += 1;
+    }
+
+    labels
+}
+
+fn group(labels: HashMap<Hex, usize>) -> Vec<HashSet<Hex>> {
+    let count: usize = labels.values().map(|id| id + 1).max().unwrap_or(0);
+    let mut groups: Vec<HashSet<Hex>> = vec![HashSet::new(); count];
+    for (hex, id) in labels {
+        groups[id].insert(hex);
+    }
+    groups
+}
+
+fn signed_area(ring: &[Point]) -> f64 {
+    let mut area: f64 = 0.0;
+    for i in 0..ring.len() {
+        let a: Point = ring[i];
+        let b: Point = ring[(i + 1) % ring.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area / 2.0
+}
+
+fn contains_point(ring: &[Point], point: Point) -> bool {
+    let mut inside: bool = false;
+    let mut j: usize = ring.len() - 1;
+    for i in 0..ring.len() {
+        let a: Point = ring[i];
+        let b: Point = ring[j];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
@@ -0,0 +1,299 @@
+//! Batch kernels behind `LayoutTool::*_batch` and `HexRound::round_batch`.
+//!
+//! On x86_64 two lanes are processed at a time with SSE2, which every x86_64
+//! CPU has. The kernels perform the same IEEE operations in the same order as
+//! the single-item functions, so results are identical. Other targets and the
+//! last odd item use the single-item functions directly.
+
+use crate::hexagon::{FractionalHex, Hex, HexRound};
+use crate::layout::{LayoutTool, TransformedLayout};
+use crate::point::Point;
+
+pub(crate) fn hex_to_pixel(
+    layout: TransformedLayout,
+    q: &[i32],
+    r: &[i32],
+    x: &mut [f64],
+    y: &mut [f64],
+) {
+    let len: usize = q.len();
+    assert!(
+        r.len() == len && x.len() == len && y.len() == len,
+        "slice lengths differ"
+    );
+
+    let done: usize = kernel::hex_to_pixel(layout, q, r, x, y);
+    for i in done..len {
+        let point: Point = LayoutTool::hex_to_pixel(layout, Hex::new(q[i], r[i]));
+        x[i] = point.x;
+        y[i] = point.y;
+    }
+}
+
+pub(crate) fn pixel_to_hex(
+    layout: TransformedLayout,
+    x: &[f64],
+    y: &[f64],
+    q: &mut [f64],
+    r: &mut [f64],
+) {
+    let len: usize = x.len();
+    assert!(
+        y.len() == len && q.len() == len && r.len() == len,
+        "slice lengths differ"
+    );
+
+    let done: usize = kernel::pixel_to_hex(layout, x, y, q, r);
+    for i in done..len {
+        let hex: FractionalHex = LayoutTool::pixel_to_hex(layout, Point { x: x[i], y: y[i] });
+        q[i] = hex.q();
+        r[i] = hex.r();
+    }
+}
+
+pub(crate) fn round(q: &[f64], r: &[f64], out_q: &mut [i32], out_r: &mut [i32]) {
+    let len: usize = q.len();
+    assert!(
+        r.len() == len && out_q.len() == len && out_r.len() == len,
+        "slice lengths differ"
+    );
+
+    let done: usize = kernel::round(q, r, out_q, out_r);
+    for i in done..len {
+        let hex: Hex = FractionalHex::new(q[i], r[i]).round();
+        out_q[i] = hex.q();
+        out_r[i] = hex.r();
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod kernel {
+    use std::arch::x86_64::*;
+
+    use crate::layout::{Orientation, Projection, TransformedLayout};
+
+    // Items handled by the kernels, the rest is left to the scalar path.
+    fn lanes(len: usize) -> usize {
+        len - len % 2
+    }
+
+    pub(super) fn hex_to_pixel(
+        layout: TransformedLayout,
+        q: &[i32],
+        r: &[i32],
+        x: &mut [f64],
+        y: &mut [f64],
+    ) -> usize {
+        let p: Projection = layout.projection();
+        let o: Orientation = p.orientation;
+
+        // SAFETY: SSE2 is part of the x86_64 baseline and every load and
+        // store stays within the first `lanes` items of equally long slices.
+        unsafe {
+            let sign: __m128d = _mm_set1_pd(-0.0);
+            for i in (0..lanes(q.len())).step_by(2) {
+                let qv: __m128d = _mm_set_pd(q[i + 1] as f64, q[i] as f64);
+                let rv: __m128d = _mm_set_pd(r[i + 1] as f64, r[i] as f64);
+
+                let px: __m128d = _mm_mul_pd(
+                    _mm_add_pd(
+                        _mm_mul_pd(_mm_set1_pd(o.f0), qv),
+                        _mm_mul_pd(_mm_set1_pd(o.f1), rv),
+                    ),
+                    _mm_set1_pd(p.size.x),
+                );
+                let py: __m128d = _mm_mul_pd(
+                    _mm_add_pd(
+                        _mm_mul_pd(_mm_set1_pd(o.f2), qv),
+                        _mm_mul_pd(_mm_set1_pd(o.f3), rv),
+                    ),
+                    _mm_set1_pd(p.size.y),
+                );
+                let mut px: __m128d = _mm_mul_pd(px, _mm_set1_pd(p.spacing.x));
+                let mut py: __m128d = _mm_mul_pd(py, _mm_set1_pd(p.spacing.y));
+
+                if p.active {
+                    let tx: __m128d = _mm_sub_pd(
+                        _mm_mul_pd(px, _mm_set1_pd(p.cos)),
+                        _mm_mul_pd(py, _mm_set1_pd(p.sin)),
+                    );
+                    let ty: __m128d = _mm_mul_pd(
+                        _mm_add_pd(
+                            _mm_mul_pd(px, _mm_set1_pd(p.sin)),
+                            _mm_mul_pd(py, _mm_set1_pd(p.cos)),
+                        ),
+                        _mm_set1_pd(p.tilt),
+                    );
+                    px = tx;
+                    py = if p.flip { _mm_xor_pd(ty, sign) } else { ty };
+                }
+
+                _mm_storeu_pd(
+                    x.as_mut_ptr().add(i),
+                    _mm_add_pd(px, _mm_set1_pd(p.origin.x)),
+                );
+                _mm_storeu_pd(
+                    y.as_mut_ptr().add(i),
+                    _mm_add_pd(py, _mm_set1_pd(p.origin.y)),
+                );
+            }
+        }
+
+        lanes(q.len())
+    }
+
+    pub(super) fn pixel_to_hex(
+        layout: TransformedLayout,
+        x: &[f64],
+        y: &[f64],
+        q: &mut [f64],
+        r: &mut [f64],
+    ) -> usize {
+        let p: Projection = layout.projection();
+        let o: Orientation = p.orientation;
+
+        // SAFETY: see `hex_to_pixel`.
+        unsafe {
+            let sign: __m128d = _mm_set1_pd(-0.0);
+            for i in (0..lanes(x.len())).step_by(2) {
+                let mut ux: __m128d =
+                    _mm_sub_pd(_mm_loadu_pd(x.as_ptr().add(i)), _mm_set1_pd(p.origin.x));
+                let mut uy: __m128d =
+                    _mm_sub_pd(_mm_loadu_pd(y.as_ptr().add(i)), _mm_set1_pd(p.origin.y));
+
+                if p.active {
+                    let px: __m128d = ux;
+                    let py: __m128d = if p.flip { _mm_xor_pd(uy, sign) } else { uy };
+                    let py: __m128d = _mm_div_pd(py, _mm_set1_pd(p.tilt));
+                    ux = _mm_add_pd(
+                        _mm_mul_pd(px, _mm_set1_pd(p.cos)),
+                        _mm_mul_pd(py, _mm_set1_pd(p.sin)),
+                    );
+                    uy = _mm_sub_pd(
+                        _mm_mul_pd(py, _mm_set1_pd(p.cos)),
+                        _mm_mul_pd(px, _mm_set1_pd(p.sin)),
+                    );
+                }
+
+                let ptx: __m128d = _mm_div_pd(
+                    _mm_div_pd(ux, _mm_set1_pd(p.spacing.x)),
+                    _mm_set1_pd(p.size.x),
+                );
+                let pty: __m128d = _mm_div_pd(
+                    _mm_div_pd(uy, _mm_set1_pd(p.spacing.y)),
+                    _mm_set1_pd(p.size.y),
+                );
+
+                _mm_storeu_pd(
+                    q.as_mut_ptr().add(i),
+                    _mm_add_pd(
+                        _mm_mul_pd(_mm_set1_pd(o.b0), ptx),
+                        _mm_mul_pd(_mm_set1_pd(o.b1), pty),
+                    ),
+                );
+                _mm_storeu_pd(
+                    r.as_mut_ptr().add(i),
+                    _mm_add_pd(
+                        _mm_mul_pd(_mm_set1_pd(o.b2), ptx),
+                        _mm_mul_pd(_mm_set1_pd(o.b3), pty),
+                    ),
+                );
+            }
+        }
+
+        lanes(x.len())
+    }
+
+    pub(super) fn round(q: &[f64], r: &[f64], out_q: &mut [i32], out_r: &mut [i32]) -> usize {
+        // SAFETY: see `hex_to_pixel`.
+        unsafe {
+            let sign: __m128d = _mm_set1_pd(-0.0);
+            for i in (0..lanes(q.len())).step_by(2) {
+                let qv: __m128d = _mm_loadu_pd(q.as_ptr().add(i));
+                let rv: __m128d = _mm_loadu_pd(r.as_ptr().add(i));
+                let sv: __m128d = _mm_sub_pd(_mm_xor_pd(qv, sign), rv);
+
+                let qr: __m128d = round_away(qv);
+                let rr: __m128d = round_away(rv);
+                let sr: __m128d = round_away(sv);
+
+                let mut q_diff: [f64; 2] = [0.0; 2];
+                let mut r_diff: [f64; 2] = [0.0; 2];
+                let mut s_diff: [f64; 2] = [0.0; 2];
+                _mm_storeu_pd(q_diff.as_mut_ptr(), _mm_andnot_pd(sign, _mm_sub_pd(qr, qv)));
+                _mm_storeu_pd(r_diff.as_mut_ptr(), _mm_andnot_pd(sign, _mm_sub_pd(rr, rv)));
+                _mm_storeu_pd(s_diff.as_mut_ptr(), _mm_andnot_pd(sign, _mm_sub_pd(sr, sv)));
+
+                let mut rounded: [[f64; 2]; 3] = [[0.0; 2]; 3];
+                _mm_storeu_pd(rounded[0].as_mut_ptr(), qr);
+                _mm_storeu_pd(rounded[1].as_mut_ptr(), rr);
+                _mm_storeu_pd(rounded[2].as_mut_ptr(), sr);
+
+                for lane in 0..2 {
+                    let qi: i32 = rounded[0][lane] as i32;
+                    let ri: i32 = rounded[1][lane] as i32;
+                    let si: i32 = rounded[2][lane] as i32;
+
+                    let (qi, ri) = if q_diff[lane] > r_diff[lane] && q_diff[lane] > s_diff[lane] {
+                        (-ri - si, ri)
+                    } else if r_diff[lane] > s_diff[lane] {
+                        (qi, -qi - si)
+                    } else {
+                        (qi, ri)
+                    };
+                    out_q[i + lane] = qi;
+                    out_r[i + lane] = ri;
+                }
+            }
+        }
+
+        lanes(q.len())
+    }
+
+    // `f64::round`: halfway values away from zero.
+    unsafe fn round_away(v: __m128d) -> __m128d {
+        let sign: __m128d = _mm_set1_pd(-0.0);
+        let big: __m128d = _mm_set1_pd(4_503_599_627_370_496.0);
+        let abs: __m128d = _mm_andnot_pd(sign, v);
+
+        // Adding and removing 2^52 rounds to nearest, ties to even.
+        let even: __m128d = _mm_sub_pd(_mm_add_pd(abs, big), big);
+        let tie_down: __m128d = _mm_cmpeq_pd(_mm_sub_pd(even, abs), _mm_set1_pd(-0.5));
+        let away: __m128d = _mm_add_pd(even, _mm_and_pd(tie_down, _mm_set1_pd(1.0)));
+
+        // Values from 2^52 on are whole already.
+        let whole: __m128d = _mm_cmpge_pd(abs, big);
+        let rounded: __m128d = _mm_or_pd(_mm_and_pd(whole, abs), _mm_andnot_pd(whole, away));
+
+        _mm_or_pd(rounded, _mm_and_pd(sign, v))
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+mod kernel {
+    use crate::layout::TransformedLayout;
+
+    pub(super) fn hex_to_pixel(
+        _: TransformedLayout,
+        _: &[i32],
+        _: &[i32],
+        _: &mut [f64],
+        _: &mut [f64],
+    ) -> usize {
+        0
+    }
+
+    pub(super) fn pixel_to_hex(
+        _: TransformedLayout,
+        _: &[f64],
+        _: &[f64],
+        _: &mut [f64],
+        _: &mut [f64],
+    ) -> usize {
+        0
+    }
+
+    pub(super) fn round(_: &[f64], _: &[f64], _: &mut [i32], _: &mut [i32]) -> usize {
+        0
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::hexagon::{Hex, HexUtility};
+use crate::hierarchy::HexHierarchy;
+
+/// Binary encoding of a tile payload.
+pub trait TileCodec: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    /// Reads one value from the front of `input` and advances past it.
+    fn decode(input: &mut &[u8]) -> io::Result<Self>;
+}
+
+macro_rules! impl_tile_codec {
+    ($($t:ty),*) => {
+        $(
+            impl TileCodec for $t {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(input: &mut &[u8]) -> io::Result<Self> {
+                    let mut bytes = [0; std::mem::size_of::<$t>()];
+                    let len: usize = bytes.len();
+                    bytes.copy_from_slice(take(input, len)?);
+                    Ok(<$t>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_tile_codec!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+/// Splits `len` bytes off the front of `input`.
+pub fn take<'a>(input: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+    if input.len() < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "truncated tile data",
+        ));
+    }
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Ok(head)
+}
+
+/// Where chunks live while they are not loaded. Chunks are addressed by their
+/// `HexHierarchy` super-hex and hold tiles by world hex.
+pub trait ChunkStorage<T> {
+    fn load(&mut self, chunk: Hex) -> io::Result<Option<HashMap<Hex, T>>>;
+    fn save(&mut self, chunk: Hex, tiles: &HashMap<Hex, T>) -> io::Result<()>;
+}
+
+/// Keeps unloaded chunks in memory, mostly useful for tests and tools.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage<T> {
+    pub chunks: HashMap<Hex, HashMap<Hex, T>>,
+}
+
+impl<T: Clone> ChunkStorage<T> for MemoryStorage<T> {
+    fn load(&mut self, chunk: Hex) -> io::Result<Option<HashMap<Hex, T>>> {
+        Ok(self.chunks.get(&chunk).cloned())
+    }
+
+    fn save(&mut self, chunk: Hex, tiles: &HashMap<Hex, T>) -> io::Result<()> {
+        self.chunks.insert(chunk, tiles.clone());
+        Ok(())
+    }
+}
+
+/// Stores one file per chunk in `directory`, named after the chunk
+/// coordinates. Tiles are written as q, r and their `TileCodec` payload.
+#[derive(Clone, Debug)]
+pub struct FileStorage {
+    pub directory: PathBuf,
+}
+
+impl FileStorage {
+    pub fn path(&self, chunk: Hex) -> PathBuf {
+        self.directory
+            .join(format!("chunk_{}_{}.bin", chunk.q(), chunk.r()))
+    }
+}
+
+impl<T: TileCodec> ChunkStorage<T> for FileStorage {
+    fn load(&mut self, chunk: Hex) -> io::Result<Option<HashMap<Hex, T>>> {
+        let bytes: Vec<u8> = match fs::read(self.path(chunk)) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+
+        let mut input: &[u8] = &bytes;
+        let count: u32 = u32::decode(&mut input)?;
+        let mut tiles: HashMap<Hex, T> = HashMap::new();
+        for _ in 0..count {
+            let q: i32 = i32::decode(&mut input)?;
+            let r: i32 = i32::decode(&mut input)?;
+            tiles.insert(Hex::new(q, r), T::decode(&mut input)?);
+        }
+
+        Ok(Some(tiles))
+    }
+
+    fn save(&mut self, chunk: Hex, tiles: &HashMap<Hex, T>) -> io::Result<()> {
+        let mut sorted: Vec<(&Hex, &T)> = tiles.iter().collect();
+        sorted.sort_by_key(|(hex, _)| (hex.q(), hex.r()));
+
+        let mut bytes: Vec<u8> = vec![];
+        (sorted.len() as u32).encode(&mut bytes);
+        for (hex, tile) in sorted {
+            hex.q().encode(&mut bytes);
+            hex.r().encode(&mut bytes);
+            tile.encode(&mut bytes);
+        }
+
+        fs::create_dir_all(&self.directory)?;
+        fs::write(self.path(chunk), bytes)
+    }
+}
+
+/// A map split into hexagonal chunks that are loaded from and saved to a
+/// `ChunkStorage` as needed, so only part of it has to be in memory.
+pub struct ChunkedMap<T, S: ChunkStorage<T>> {
+    storage: S,
+    radius: i32,
+    chunks: HashMap<Hex, HashMap<Hex, T>>,
+    dirty: HashSet<Hex>,
+}
+
+impl<T, S: ChunkStorage<T>> ChunkedMap<T, S> {
+    /// `radius` is the `HexHierarchy` radius of every chunk.
+    pub fn new(storage: S, radius: i32) -> ChunkedMap<T, S> {
+        ChunkedMap {
+            storage,
+            radius,
+            chunks: HashMap::new(),
+            dirty: HashSet::new(),
+        }
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    pub fn chunk_of(&self, hex: Hex) -> Hex {
+        HexHierarchy::split(self.radius, hex).0
+    }
+
+    pub fn get(&mut self, hex: Hex) -> io::Result<Option<&T>> {
+        let chunk: Hex = self.chunk_of(hex);
+        Ok(self.load(chunk)?.get(&hex))
+    }
+
+    pub fn insert(&mut self, hex: Hex, tile: T) -> io::Result<Option<T>> {
+        let chunk: Hex = self.chunk_of(hex);
+        let previous: Option<T> = self.load(chunk)?.insert(hex, tile);
+        self.dirty.insert(chunk);
+        Ok(previous)
+    }
+
+    pub fn remove(&mut self, hex: Hex) -> io::Result<Option<T>> {
+        let chunk: Hex = self.chunk_of(hex);
+        let removed: Option<T> = self.load(chunk)?.remove(&hex);
+        if removed.is_some() {
+            self.dirty.insert(chunk);
+        }
+        Ok(removed)
+    }
+
+    /// Makes sure the chunk is in memory, reading it from storage when it is
+    /// not. Chunks missing from storage start empty.
+    pub fn load(&mut self, chunk: Hex) -> io::Result<&mut HashMap<Hex, T>> {
+        if !self.chunks.contains_key(&chunk) {
+            let tiles: HashMap<Hex, T> = self.storage.load(chunk)?.unwrap_or_default();
+            self.chunks.insert(chunk, tiles);
+        }
+        Ok(self.chunks.get_mut(&chunk).unwrap())
+    }
+
+    /// Drops the chunk from memory, saving it first when it changed.
+    pub fn unload(&mut self, chunk: Hex) -> io::Result<()> {
+        self.save_chunk(chunk)?;
+        self.chunks.remove(&chunk);
+        Ok(())
+    }
+
+    /// Loads every chunk within `distance` chunks of the one holding `center`
+    /// and unloads all others.
+    pub fn stream(&mut self, center: Hex, distance: i32) -> io::Result<()> {
+        let wanted: Vec<Hex> = self.chunk_of(center).range(distance);
+
+        let mut stale: Vec<Hex> = self
+            .chunks
+            .keys()
+            .filter(|chunk| !wanted.contains(chunk))
+            .copied()
+            .collect();
+        stale.sort_by_key(|chunk| (chunk.q(), chunk.r()));
+        for chunk in stale {
+            self.unload(chunk)?;
+        }
+
+        for chunk in wanted {
+            self.load(chunk)?;
+        }
+        Ok(())
+    }
+
+    pub fn loaded_chunks(&self) -> Vec<Hex> {
+        let mut loaded: Vec<Hex> = self.chunks.keys().copied().collect();
+        loaded.sort_by_key(|chunk| (chunk.q(), chunk.r()));
+        loaded
+    }
+
+    /// Loaded chunks changed since they were last saved.
+    pub fn dirty_chunks(&self) -> Vec<Hex> {
+        let mut dirty: Vec<Hex> = self.dirty.iter().copied().collect();
+        dirty.sort_by_key(|chunk| (chunk.q(), chunk.r()));
+        dirty
+    }
+
+    /// Writes every dirty chunk to storage, keeping them loaded.
+    pub fn save(&mut self) -> io::Result<()> {
+        for chunk in self.dirty_chunks() {
+            self.save_chunk(chunk)?;
+        }
+        Ok(())
+    }
+
+    fn save_chunk(&mut self, chunk: Hex) -> io::Result<()> {
+        if !self.dirty.contains(&chunk) {
+            return Ok(());
+        }
+        if let Some(tiles) = self.chunks.get(&chunk) {
+            self.storage.save(chunk, tiles)?;
+        }
+        self.dirty.remove(&chunk);
+        Ok(())
+    }
+}
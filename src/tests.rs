@@ -1,14 +1,41 @@
 extern crate float_eq;
 use float_eq::assert_float_eq;
 
+use crate::area::HexArea;
+use crate::curve::HexCurve;
+use crate::fixed::{
+    Fixed, FixedLayout, FixedLayoutTool, FixedPoint, FIXED_ORIENTATION_FLAT,
+    FIXED_ORIENTATION_POINTY, FIXED_SQRT_3,
+};
+use crate::flow::FlowField;
+use crate::fog::{FieldOfView, FogOfWar, Visibility};
+use crate::format::{rle_decode, rle_encode, MapFile, MapShape};
+use crate::gbt::{HexGbt, GBT_MAX_RESOLUTION};
+use crate::geojson::{GeoJson, GeoValue};
 use crate::hexagon::{
     DoubledCoord, FractionalHex, Hex, HexMath, HexRotation, HexRound, HexUtility, OffsetCoord,
+    RoundMode, TieBreak,
+};
+use crate::hierarchy::HexHierarchy;
+use crate::layout::{
+    AxisConvention, CachedLayout, Layout, LayoutTool, LayoutTransform, TransformedLayout,
+    LAYOUT_ORIENTATION_FLAT, LAYOUT_ORIENTATION_POINTY, LAYOUT_TRANSFORM_ISOMETRIC,
+    LAYOUT_TRANSFORM_NONE,
 };
-use crate::layout::{Layout, LayoutTool, LAYOUT_ORIENTATION_FLAT, LAYOUT_ORIENTATION_POINTY};
+use crate::line::HexLine;
+use crate::motion::{Easing, PathFollower};
+use crate::partition::{HexPartition, PartitionCell};
+use crate::pick::{HexPicker, PickTarget};
 use crate::point::Point;
-use crate::tools::{HexDirection, HexDoubled, HexOffset, HEX_EVEN, HEX_ODD};
+use crate::region::HexRegion;
+use crate::storage::{ChunkStorage, ChunkedMap, FileStorage, MemoryStorage, TileCodec};
+use crate::tiled::{TiledLayer, TiledMap};
+use crate::tools::{HexDirection, HexDoubled, HexOffset, HexSector, OffsetAxis, HEX_EVEN, HEX_ODD};
+use crate::wrap::{Wrap, WrapTool};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+use std::io;
 
 #[test]
 fn test_s_component() {
@@ -369,3 +396,1841 @@ pub fn test_hashing() {
 
     assert_eq!(*map.get(&hex1).unwrap(), "foo");
 }
+
+#[test]
+fn test_region_outline() {
+    let pointy: Layout = Layout {
+        orientation: LAYOUT_ORIENTATION_POINTY,
+        size: Point { x: 10.0, y: 10.0 },
+        origin: Point { x: 0.0, y: 0.0 },
+    };
+
+    let single: HashSet<Hex> = [Hex::new(0, 0)].iter().copied().collect();
+    let outline_1 = HexRegion::outline(pointy, &single);
+    assert_eq!(1, outline_1.len());
+    assert_eq!(6, outline_1[0].outer.len());
+    assert!(outline_1[0].holes.is_empty());
+    for corner in LayoutTool::polygon_corners(pointy, Hex::new(0, 0)) {
+        assert!(outline_1[0].outer.contains(&corner));
+    }
+
+    let pair: HashSet<Hex> = [Hex::new(0, 0), Hex::new(1, 0)].iter().copied().collect();
+    let outline_2 = HexRegion::outline(pointy, &pair);
+    assert_eq!(1, outline_2.len());
+    assert_eq!(10, outline_2[0].outer.len());
+
+    let flat: Layout = Layout {
+        orientation: LAYOUT_ORIENTATION_FLAT,
+        ..pointy
+    };
+    let ring: HashSet<Hex> = (0..6).map(HexDirection::direction).collect();
+    let outline_3 = HexRegion::outline(flat, &ring);
+    assert_eq!(1, outline_3.len());
+    assert_eq!(18, outline_3[0].outer.len());
+    assert_eq!(1, outline_3[0].holes.len());
+    assert_eq!(6, outline_3[0].holes[0].len());
+}
+
+#[test]
+fn test_hex_range() {
+    let center = Hex::new(2, -1);
+    let range = center.range(2);
+
+    assert_eq!(19, range.len());
+    assert!(range.iter().all(|h| center.distance(*h) <= 2));
+    assert!(range.contains(&Hex::new(4, -3)));
+}
+
+#[test]
+fn test_region_flood_fill() {
+    let wall: HashSet<Hex> = Hex::new(0, 0)
+        .range(2)
+        .into_iter()
+        .filter(|h| h.length() == 2)
+        .collect();
+
+    let inside = HexRegion::flood_fill(Hex::new(0, 0), 10, |h| !wall.contains(&h));
+    assert_eq!(7, inside.len());
+
+    let bounded = HexRegion::flood_fill(Hex::new(5, 0), 1, |h| !wall.contains(&h));
+    assert_eq!(7, bounded.len());
+
+    assert!(HexRegion::flood_fill(Hex::new(2, 0), 10, |h| !wall.contains(&h)).is_empty());
+}
+
+#[test]
+fn test_region_components() {
+    let hexes: HashSet<Hex> = [
+        Hex::new(0, 0),
+        Hex::new(1, 0),
+        Hex::new(5, 5),
+        Hex::new(5, 6),
+    ]
+    .iter()
+    .copied()
+    .collect();
+
+    let components = HexRegion::components(&hexes);
+    assert_eq!(2, components.len());
+    assert!(components[0].contains(&Hex::new(0, 0)));
+    assert!(components[0].contains(&Hex::new(1, 0)));
+    assert!(components[1].contains(&Hex::new(5, 6)));
+
+    let labels = HexRegion::label_components(Hex::new(0, 0), 3, |h| h.q() != 0);
+    assert_eq!(labels[&Hex::new(-1, 0)], labels[&Hex::new(-3, 3)]);
+    assert_ne!(labels[&Hex::new(-1, 0)], labels[&Hex::new(1, 0)]);
+    assert!(!labels.contains_key(&Hex::new(0, 2)));
+}
+
+#[test]
+fn test_region_holes() {
+    let ring: HashSet<Hex> = Hex::new(3, 3)
+        .range(2)
+        .into_iter()
+        .filter(|h| h.distance(Hex::new(3, 3)) > 0)
+        .collect();
+
+    let holes = HexRegion::holes(&ring);
+    assert_eq!(1, holes.len());
+    assert!(holes[0].contains(&Hex::new(3, 3)));
+    assert_eq!(1, holes[0].len());
+
+    let solid: HashSet<Hex> = Hex::new(3, 3).range(2).into_iter().collect();
+    assert!(HexRegion::holes(&solid).is_empty());
+
+    // Every set hashes differently, the order of the holes stays the same.
+    for _ in 0..10 {
+        let rings: HashSet<Hex> = [Hex::new(4, 0), Hex::new(0, 0)]
+            .iter()
+            .flat_map(|center| center.range(1).into_iter().filter(move |h| h != center))
+            .collect();
+        let holes = HexRegion::holes(&rings);
+        assert_eq!(2, holes.len());
+        assert!(holes[0].contains(&Hex::new(0, 0)));
+        assert!(holes[1].contains(&Hex::new(4, 0)));
+    }
+}
+
+#[test]
+fn test_partition_nearest() {
+    let map: HashSet<Hex> = Hex::new(0, 0).range(4).into_iter().collect();
+    let seeds = [Hex::new(-2, 0), Hex::new(2, 0)];
+
+    let cells = HexPartition::nearest(&seeds, &map);
+    assert_eq!(map.len(), cells.len());
+    assert_eq!(
+        PartitionCell {
+            seed: 0,
+            distance: 0
+        },
+        cells[&Hex::new(-2, 0)]
+    );
+    assert_eq!(
+        PartitionCell {
+            seed: 1,
+            distance: 2
+        },
+        cells[&Hex::new(4, -2)]
+    );
+    assert_eq!(
+        PartitionCell {
+            seed: 0,
+            distance: 2
+        },
+        cells[&Hex::new(0, 0)]
+    );
+
+    for (hex, cell) in cells.iter() {
+        let nearest = seeds.iter().map(|s| s.distance(*hex)).min().unwrap();
+        assert_eq!(nearest, cell.distance);
+    }
+}
+
+#[test]
+fn test_partition_weighted() {
+    let map: HashSet<Hex> = Hex::new(0, 0).range(4).into_iter().collect();
+    let seeds = [Hex::new(-2, 0), Hex::new(2, 0)];
+
+    let cells = HexPartition::weighted(&seeds, |hex| {
+        if !map.contains(&hex) {
+            None
+        } else if hex.q() < 0 {
+            Some(3)
+        } else {
+            Some(1)
+        }
+    });
+
+    assert_eq!(1, cells[&Hex::new(0, 0)].seed);
+    assert_eq!(2, cells[&Hex::new(0, 0)].distance);
+    assert_eq!(
+        PartitionCell {
+            seed: 0,
+            distance: 3
+        },
+        cells[&Hex::new(-3, 0)]
+    );
+}
+
+#[test]
+fn test_flow_field() {
+    let costs: HashMap<Hex, i32> = Hex::new(0, 0)
+        .range(3)
+        .into_iter()
+        .map(|h| (h, 1))
+        .collect();
+    let goal = Hex::new(0, 0);
+    let mut field = FlowField::new(costs, &[goal]);
+
+    for (hex, distance) in field.distances() {
+        assert_eq!(goal.distance(*hex), *distance);
+    }
+    assert_eq!(None, field.direction(goal));
+    assert_eq!(Some(3), field.direction(Hex::new(2, 0)));
+    assert_eq!(
+        Hex::new(1, 0),
+        HexDirection::neighbor(Hex::new(2, 0), field.direction(Hex::new(2, 0)).unwrap())
+    );
+
+    let changed = field.set_cost(Hex::new(1, 0), None);
+    assert!(changed.contains(&Hex::new(1, 0)));
+    assert!(changed.contains(&Hex::new(2, 0)));
+    assert_eq!(None, field.distance(Hex::new(1, 0)));
+    assert_eq!(Some(3), field.distance(Hex::new(2, 0)));
+    assert_ne!(Some(3), field.direction(Hex::new(2, 0)));
+
+    let restored = field.set_cost(Hex::new(1, 0), Some(1));
+    assert_eq!(changed, restored);
+    assert_eq!(Some(2), field.distance(Hex::new(2, 0)));
+    assert!(field.set_cost(Hex::new(1, 0), Some(1)).is_empty());
+}
+
+#[test]
+fn test_flow_field_matches_rebuild() {
+    let mut costs: HashMap<Hex, i32> = Hex::new(0, 0)
+        .range(4)
+        .into_iter()
+        .map(|h| (h, 1))
+        .collect();
+    let goals = [Hex::new(-3, 0), Hex::new(2, 2)];
+    let mut field = FlowField::new(costs.clone(), &goals);
+
+    let edits = [
+        (Hex::new(0, 0), Some(5)),
+        (Hex::new(-1, 0), None),
+        (Hex::new(0, 0), Some(1)),
+        (Hex::new(2, 2), None),
+    ];
+    for (hex, cost) in edits.iter() {
+        field.set_cost(*hex, *cost);
+        match cost {
+            Some(cost) => costs.insert(*hex, *cost),
+            None => costs.remove(hex),
+        };
+        assert_eq!(
+            FlowField::new(costs.clone(), &goals).distances(),
+            field.distances()
+        );
+    }
+}
+
+#[test]
+fn test_wrap_cylinder() {
+    let wrap = Wrap {
+        axis: OffsetAxis::R,
+        offset: HEX_ODD,
+        columns: Some(10),
+        rows: None,
+    };
+
+    let west = HexOffset::r_to_cube(HEX_ODD, OffsetCoord { col: 0, row: 3 });
+    let east = HexOffset::r_to_cube(HEX_ODD, OffsetCoord { col: 9, row: 3 });
+
+    assert_eq!(
+        west,
+        WrapTool::normalize(
+            wrap,
+            HexOffset::r_to_cube(HEX_ODD, OffsetCoord { col: 10, row: 3 })
+        )
+    );
+    assert_eq!(west, WrapTool::neighbor(wrap, east, 0));
+    assert_eq!(1, WrapTool::distance(wrap, west, east));
+    assert_eq!(vec![east, west], WrapTool::line(wrap, east, west));
+    assert_eq!(
+        5,
+        WrapTool::distance(
+            wrap,
+            west,
+            HexOffset::r_to_cube(HEX_ODD, OffsetCoord { col: 5, row: 3 })
+        )
+    );
+
+    let around = WrapTool::range(wrap, west, 6);
+    assert_eq!(around.len(), around.iter().collect::<HashSet<_>>().len());
+    assert!(around.len() < west.range(6).len());
+}
+
+#[test]
+fn test_wrap_torus() {
+    for offset in [HEX_EVEN, HEX_ODD].iter() {
+        let wrap = Wrap {
+            axis: OffsetAxis::Q,
+            offset: *offset,
+            columns: Some(8),
+            rows: Some(6),
+        };
+
+        let corner = HexOffset::q_to_cube(*offset, OffsetCoord { col: 0, row: 0 });
+        let opposite = HexOffset::q_to_cube(*offset, OffsetCoord { col: 7, row: 5 });
+        assert!(WrapTool::distance(wrap, corner, opposite) <= 2);
+
+        for hex in Hex::new(0, 0).range(12) {
+            let normal = WrapTool::normalize(wrap, hex);
+            let coord = HexOffset::q_from_cube(*offset, normal);
+            assert!(coord.col >= 0 && coord.col < 8);
+            assert!(coord.row >= 0 && coord.row < 6);
+            for direction in 0..6 {
+                assert_eq!(
+                    1,
+                    WrapTool::distance(wrap, hex, WrapTool::neighbor(wrap, hex, direction))
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_hierarchy_split() {
+    for radius in 0..4 {
+        let mut sizes: HashMap<Hex, i32> = HashMap::new();
+        for hex in Hex::new(0, 0).range(12) {
+            let (super_hex, local) = HexHierarchy::split(radius, hex);
+            assert!(local.length() <= radius);
+            assert_eq!(hex, HexHierarchy::join(radius, super_hex, local));
+            *sizes.entry(super_hex).or_insert(0) += 1;
+        }
+        assert_eq!(HexHierarchy::area(radius), sizes[&Hex::new(0, 0)]);
+    }
+
+    let (super_hex, local) = HexHierarchy::split(2, Hex::new(5, -2));
+    assert_eq!(Hex::new(1, 0), super_hex);
+    assert_eq!(Hex::new(0, 0), local);
+
+    let neighbor = HexHierarchy::neighbor(super_hex, 3);
+    assert_eq!(Hex::new(0, 0), neighbor);
+}
+
+#[test]
+fn test_hierarchy_levels() {
+    let hex = Hex::new(37, -81);
+    let (top, locals) = HexHierarchy::split_levels(1, 3, hex);
+
+    assert_eq!(3, locals.len());
+    assert!(locals.iter().all(|local| local.length() <= 1));
+    assert_eq!(hex, HexHierarchy::join_levels(1, top, &locals));
+    assert_eq!(
+        top,
+        HexHierarchy::split(1, HexHierarchy::split(1, HexHierarchy::split(1, hex).0).0).0
+    );
+}
+
+#[test]
+fn test_gbt_roundtrip() {
+    for resolution in [0, 1, 4, GBT_MAX_RESOLUTION].iter() {
+        for hex in Hex::new(0, 0).range(20) {
+            let index = HexGbt::encode(hex, *resolution).unwrap();
+            assert_eq!(*resolution, HexGbt::resolution(index));
+            assert_eq!(Some((hex, *resolution)), HexGbt::decode(index));
+        }
+    }
+
+    assert_eq!(
+        vec![0, 0],
+        HexGbt::digits(HexGbt::encode(Hex::new(0, 0), 2).unwrap())
+    );
+    assert_eq!(
+        vec![1],
+        HexGbt::digits(HexGbt::encode(Hex::new(1, 0), 1).unwrap())
+    );
+    assert_eq!(None, HexGbt::encode(Hex::new(1000, 0), 0));
+    assert_eq!(None, HexGbt::encode(Hex::new(0, 0), GBT_MAX_RESOLUTION + 1));
+}
+
+#[test]
+fn test_gbt_hierarchy() {
+    let hex = Hex::new(9, -4);
+    let index = HexGbt::encode(hex, 3).unwrap();
+
+    let parent = HexGbt::parent(index).unwrap();
+    assert_eq!(
+        HexGbt::encode(HexHierarchy::split(1, hex).0, 2),
+        Some(parent)
+    );
+    assert!(HexGbt::children(parent).contains(&index));
+    assert_eq!(None, HexGbt::parent(HexGbt::encode(hex, 0).unwrap()));
+
+    let children = HexGbt::children(index);
+    assert_eq!(7, children.len());
+    for child in children {
+        assert_eq!(Some(index), HexGbt::parent(child));
+        let (child_hex, resolution) = HexGbt::decode(child).unwrap();
+        assert_eq!(4, resolution);
+        assert_eq!(hex, HexHierarchy::split(1, child_hex).0);
+    }
+}
+
+#[test]
+fn test_gbt_arithmetic() {
+    let a = HexGbt::encode(Hex::new(3, -1), 2).unwrap();
+    let b = HexGbt::encode(Hex::new(-5, 2), 2).unwrap();
+
+    assert_eq!(HexGbt::encode(Hex::new(-2, 1), 2), HexGbt::add(a, b));
+    assert_eq!(
+        None,
+        HexGbt::add(a, HexGbt::encode(Hex::new(0, 0), 3).unwrap())
+    );
+    assert_eq!(HexGbt::encode(Hex::new(3, -2), 2), HexGbt::neighbor(a, 2));
+}
+
+#[test]
+fn test_curve_roundtrip() {
+    let hexes = vec![
+        Hex::new(0, 0),
+        Hex::new(-1, 0),
+        Hex::new(7, -3),
+        Hex::new(i32::MAX, i32::MIN + 1),
+        Hex::new(-123_456, 654_321),
+    ];
+
+    for hex in hexes {
+        assert_eq!(hex, HexCurve::unpack(HexCurve::packed(hex)));
+        assert_eq!(hex, HexCurve::from_morton(HexCurve::morton(hex)));
+        assert_eq!(hex, HexCurve::from_hilbert(HexCurve::hilbert(hex)));
+    }
+
+    assert!(HexCurve::morton(Hex::new(-1, -1)) < HexCurve::morton(Hex::new(0, 0)));
+    assert_eq!(
+        HexCurve::morton(Hex::new(0, 0)) + 1,
+        HexCurve::morton(Hex::new(1, 0))
+    );
+}
+
+#[test]
+fn test_curve_hilbert_adjacency() {
+    let start = HexCurve::hilbert(Hex::new(-3, 2));
+    for key in start..start + 64 {
+        let a = HexCurve::from_hilbert(key);
+        let b = HexCurve::from_hilbert(key + 1);
+        assert_eq!(1, (a.q() - b.q()).abs() + (a.r() - b.r()).abs());
+    }
+}
+
+#[test]
+fn test_curve_ranges() {
+    let area = Hex::new(2, -1).range(3);
+
+    let morton = HexCurve::morton_ranges(&area);
+    let hilbert = HexCurve::hilbert_ranges(&area);
+
+    let count = |ranges: &Vec<(u64, u64)>| ranges.iter().map(|(a, b)| b - a + 1).sum::<u64>();
+    assert_eq!(area.len() as u64, count(&morton));
+    assert_eq!(area.len() as u64, count(&hilbert));
+    assert!(morton.len() < area.len());
+    assert!(morton.windows(2).all(|w| w[0].1 + 1 < w[1].0));
+
+    for hex in area {
+        let key = HexCurve::morton(hex);
+        assert!(morton.iter().any(|(a, b)| *a <= key && key <= *b));
+    }
+}
+
+#[test]
+fn test_chunked_map_memory() {
+    let mut map: ChunkedMap<u8, MemoryStorage<u8>> = ChunkedMap::new(MemoryStorage::default(), 2);
+
+    let near = Hex::new(1, 0);
+    let far = Hex::new(40, -20);
+    assert_eq!(None, map.insert(near, 7).unwrap());
+    assert_eq!(None, map.insert(far, 9).unwrap());
+    assert_eq!(2, map.dirty_chunks().len());
+
+    map.stream(near, 1).unwrap();
+    assert!(!map.loaded_chunks().contains(&map.chunk_of(far)));
+    assert_eq!(vec![map.chunk_of(near)], map.dirty_chunks());
+    assert!(map.storage().chunks.contains_key(&map.chunk_of(far)));
+
+    assert_eq!(Some(&9), map.get(far).unwrap());
+    assert!(map.loaded_chunks().contains(&map.chunk_of(far)));
+
+    map.save().unwrap();
+    assert!(map.dirty_chunks().is_empty());
+    assert_eq!(
+        Some(&7),
+        map.storage().chunks[&map.chunk_of(near)].get(&near)
+    );
+
+    assert_eq!(Some(7), map.remove(near).unwrap());
+    assert_eq!(vec![map.chunk_of(near)], map.dirty_chunks());
+}
+
+#[test]
+fn test_chunked_map_files() {
+    let directory =
+        std::env::temp_dir().join(format!("hexagon_tiles_chunks_{}", std::process::id()));
+    let storage = FileStorage {
+        directory: directory.clone(),
+    };
+
+    let mut map: ChunkedMap<i32, FileStorage> = ChunkedMap::new(storage.clone(), 3);
+    for hex in Hex::new(0, 0).range(8) {
+        map.insert(hex, hex.q() * 100 + hex.r()).unwrap();
+    }
+    map.save().unwrap();
+
+    let mut reopened: ChunkedMap<i32, FileStorage> = ChunkedMap::new(storage.clone(), 3);
+    for hex in Hex::new(0, 0).range(8) {
+        assert_eq!(Some(&(hex.q() * 100 + hex.r())), reopened.get(hex).unwrap());
+    }
+    assert_eq!(None, reopened.get(Hex::new(50, 50)).unwrap());
+
+    let mut raw = storage;
+    let missing: Option<HashMap<Hex, i32>> = raw.load(Hex::new(99, 99)).unwrap();
+    assert!(missing.is_none());
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_map_file_roundtrip() {
+    let layout = Layout {
+        orientation: LAYOUT_ORIENTATION_FLAT,
+        size: Point { x: 12.0, y: 10.0 },
+        origin: Point { x: -4.0, y: 8.5 },
+    };
+
+    let shapes = vec![
+        MapShape::Sparse,
+        MapShape::Hexagon {
+            center: Hex::new(1, 1),
+            radius: 4,
+        },
+        MapShape::Rectangle {
+            axis: OffsetAxis::R,
+            offset: HEX_ODD,
+            columns: 9,
+            rows: 7,
+        },
+    ];
+
+    for shape in shapes {
+        let hexes = shape
+            .hexes()
+            .unwrap_or_else(|| vec![Hex::new(-40, 3), Hex::new(7, 7)]);
+        let tiles: HashMap<Hex, u16> = hexes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % 5 != 0)
+            .map(|(i, hex)| (*hex, (i / 10) as u16))
+            .collect();
+        let map = MapFile {
+            layout: layout.into(),
+            shape,
+            schema_version: 3,
+            tiles,
+        };
+
+        for compress in [false, true].iter() {
+            let mut bytes: Vec<u8> = vec![];
+            map.write(&mut bytes, *compress).unwrap();
+            assert_eq!(map, MapFile::read(&mut bytes.as_slice()).unwrap());
+        }
+    }
+}
+
+#[test]
+fn test_map_file_migration() {
+    let map = MapFile {
+        layout: Layout {
+            orientation: LAYOUT_ORIENTATION_POINTY,
+            size: Point { x: 1.0, y: 1.0 },
+            origin: Point { x: 0.0, y: 0.0 },
+        }
+        .into(),
+        shape: MapShape::Hexagon {
+            center: Hex::new(0, 0),
+            radius: 1,
+        },
+        schema_version: 1,
+        tiles: Hex::new(0, 0)
+            .range(1)
+            .into_iter()
+            .map(|h| (h, 2u8))
+            .collect(),
+    };
+
+    let mut bytes: Vec<u8> = vec![];
+    map.write(&mut bytes, false).unwrap();
+
+    let migrated: MapFile<u32> = MapFile::read_with(&mut bytes.as_slice(), |version, data| {
+        assert_eq!(1, version);
+        Ok(u8::decode(data)? as u32 * 1000)
+    })
+    .unwrap();
+    assert_eq!(Some(&2000), migrated.tiles.get(&Hex::new(1, -1)));
+
+    assert!(MapFile::<u8>::read(&mut &b"HEXX"[..]).is_err());
+    assert!(MapFile::<u8>::read(&mut &bytes[..bytes.len() - 1]).is_err());
+
+    let mut newer: Vec<u8> = bytes.clone();
+    newer[4] = 2;
+    assert!(MapFile::<u8>::read(&mut newer.as_slice()).is_err());
+}
+
+#[test]
+fn test_rle() {
+    let data: Vec<u8> = vec![0; 300]
+        .into_iter()
+        .chain(vec![1, 2, 3, 4, 4])
+        .chain((0..=255).collect::<Vec<u8>>())
+        .collect();
+
+    let encoded = rle_encode(&data);
+    assert!(encoded.len() < data.len());
+    assert_eq!(data, rle_decode(&encoded).unwrap());
+    assert!(rle_encode(&[]).is_empty());
+}
+
+#[test]
+fn test_tiled_import() {
+    let tmx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" tiledversion="1.10.2" orientation="hexagonal" renderorder="right-down" width="3" height="2" tilewidth="32" tileheight="34" infinite="0" hexsidelength="18" staggeraxis="y" staggerindex="odd" nextlayerid="2" nextobjectid="1">
+ <tileset firstgid="1" source="terrain.tsx"/>
+ <layer id="1" name="Ground &amp; water" width="3" height="2">
+  <data encoding="csv">
+1,0,2,
+3,4,0
+</data>
+ </layer>
+</map>"#;
+
+    let map = TiledMap::from_tmx(tmx).unwrap();
+    assert_eq!(OffsetAxis::R, map.axis);
+    assert_eq!(HEX_ODD, map.offset);
+    assert_eq!(1, map.layers.len());
+    assert_eq!("Ground & water", map.layers[0].name);
+    assert_eq!(4, map.layers[0].tiles.len());
+
+    let hex = HexOffset::r_to_cube(HEX_ODD, OffsetCoord { col: 1, row: 1 });
+    assert_eq!(Some(&4), map.layers[0].tiles.get(&hex));
+
+    // Tiled draws tile (1, 1) of an odd staggered map half a tile to the
+    // right, one row height = (34 + 18) / 2 = 26 pixels down.
+    let center = LayoutTool::hex_to_pixel(map.layout(), hex);
+    assert_float_eq!(64.0, center.x, abs <= 1e-9);
+    assert_float_eq!(17.0 + 26.0, center.y, abs <= 1e-9);
+
+    assert!(TiledMap::from_tmx(&tmx.replace("hexagonal", "orthogonal")).is_err());
+    assert!(TiledMap::from_tmx(&tmx.replace("csv", "base64")).is_err());
+}
+
+#[test]
+fn test_tiled_roundtrip() {
+    for (axis, offset) in [(OffsetAxis::Q, HEX_EVEN), (OffsetAxis::R, HEX_ODD)].iter() {
+        let tiles: HashMap<Hex, u32> = (0..4)
+            .flat_map(|row| (0..5).map(move |col| OffsetCoord { col, row }))
+            .filter(|coord| (coord.col + coord.row) % 3 != 0)
+            .map(|coord| {
+                (
+                    HexOffset::to_cube(*axis, *offset, coord),
+                    (coord.col * 10 + coord.row) as u32,
+                )
+            })
+            .collect();
+
+        let map = TiledMap {
+            axis: *axis,
+            offset: *offset,
+            width: 5,
+            height: 4,
+            tile_width: 28,
+            tile_height: 24,
+            hex_side_length: 14,
+            layers: vec![TiledLayer {
+                name: "quote \"me\"".to_string(),
+                tiles,
+            }],
+        };
+
+        assert_eq!(map, TiledMap::from_tmx(&map.to_tmx()).unwrap());
+        assert_eq!(map, TiledMap::from_json(&map.to_json()).unwrap());
+    }
+
+    let flat = TiledMap {
+        axis: OffsetAxis::Q,
+        offset: HEX_EVEN,
+        width: 2,
+        height: 2,
+        tile_width: 28,
+        tile_height: 24,
+        hex_side_length: 14,
+        layers: vec![],
+    };
+    let layout = flat.layout();
+    let origin = LayoutTool::hex_to_pixel(layout, Hex::new(0, 0));
+    let next = LayoutTool::hex_to_pixel(
+        layout,
+        HexOffset::q_to_cube(HEX_EVEN, OffsetCoord { col: 1, row: 0 }),
+    );
+    assert_float_eq!(14.0, origin.x, abs <= 1e-9);
+    assert_float_eq!(24.0, origin.y, abs <= 1e-9);
+    assert_float_eq!(14.0 + 21.0, next.x, abs <= 1e-9);
+    assert_float_eq!(12.0, next.y, abs <= 1e-9);
+}
+
+#[test]
+fn test_geojson_hexes() {
+    let layout = Layout {
+        orientation: LAYOUT_ORIENTATION_POINTY,
+        size: Point { x: 1.0, y: 1.0 },
+        origin: Point { x: 0.0, y: 0.0 },
+    };
+
+    let hexes = vec![
+        (
+            Hex::new(0, 0),
+            vec![("name".to_string(), GeoValue::Text("a \"b\"".to_string()))],
+        ),
+        (
+            Hex::new(1, 0),
+            vec![("height".to_string(), GeoValue::Number(2.5))],
+        ),
+    ];
+
+    let json = GeoJson::hexes(layout, &hexes, None);
+    assert!(json.starts_with("{\"type\":\"FeatureCollection\""));
+    assert_eq!(2, json.matches("\"type\":\"Feature\"").count());
+    assert_eq!(2, json.matches("\"type\":\"Polygon\"").count());
+    assert!(json.contains("\"properties\":{\"q\":0,\"r\":0,\"name\":\"a \\\"b\\\"\"}"));
+    assert!(json.contains("\"height\":2.5"));
+
+    let first = LayoutTool::polygon_corners(layout, Hex::new(0, 0))[0];
+    let position = format!("[{},{}]", first.x, first.y);
+    assert!(json.contains(&position));
+
+    let project = |p: Point| Point {
+        x: p.x / 100.0,
+        y: -p.y / 100.0,
+    };
+    let projected = GeoJson::hexes(layout, &hexes, Some(&project));
+    assert!(projected.contains(&format!("[{},{}]", first.x / 100.0, -first.y / 100.0)));
+}
+
+#[test]
+fn test_geojson_regions() {
+    let layout = Layout {
+        orientation: LAYOUT_ORIENTATION_FLAT,
+        size: Point { x: 1.0, y: 1.0 },
+        origin: Point { x: 0.0, y: 0.0 },
+    };
+
+    let ring: HashSet<Hex> = (0..6).map(HexDirection::direction).collect();
+    let island: HashSet<Hex> = [Hex::new(5, 5), Hex::new(8, 8)].iter().copied().collect();
+    let regions = vec![
+        (ring, vec![("owner".to_string(), GeoValue::Number(1.0))]),
+        (island, vec![("owner".to_string(), GeoValue::Null)]),
+    ];
+
+    let json = GeoJson::regions(layout, &regions, None);
+    assert_eq!(2, json.matches("\"type\":\"MultiPolygon\"").count());
+    assert!(json.contains("\"coordinates\":[[[["));
+    assert!(json.contains("\"owner\":null"));
+    assert_eq!(1, json.matches("]]],[[[").count());
+    assert_eq!(2, json.matches("]],[[").count());
+}
+
+#[test]
+fn test_hex_round_ties() {
+    let edge = FractionalHex::new(0.5, 0.0);
+
+    assert_eq!(
+        Hex::new(1, 0),
+        edge.round_with(RoundMode::Nearest(TieBreak::Direction(0)))
+    );
+    assert_eq!(
+        Hex::new(0, 0),
+        edge.round_with(RoundMode::Nearest(TieBreak::Direction(3)))
+    );
+    assert_eq!(
+        Hex::new(0, 0),
+        edge.round_with(RoundMode::Nearest(TieBreak::Origin))
+    );
+    assert_eq!(
+        Hex::new(0, 0),
+        edge.round_with(RoundMode::Nearest(TieBreak::Stable))
+    );
+
+    let corner = FractionalHex::new(2.0 / 3.0, -1.0 / 3.0);
+    assert_eq!(
+        Hex::new(1, -1),
+        corner.round_with(RoundMode::Nearest(TieBreak::Direction(1)))
+    );
+    assert_eq!(
+        Hex::new(1, 0),
+        corner.round_with(RoundMode::Nearest(TieBreak::Direction(5)))
+    );
+    assert_eq!(
+        Hex::new(0, 0),
+        corner.round_with(RoundMode::Nearest(TieBreak::Stable))
+    );
+
+    let inside = FractionalHex::new(2.2, -0.9);
+    for tie_break in [TieBreak::Direction(4), TieBreak::Origin, TieBreak::Stable].iter() {
+        assert_eq!(
+            inside.round(),
+            inside.round_with(RoundMode::Nearest(*tie_break))
+        );
+    }
+}
+
+#[test]
+fn test_hex_round_modes() {
+    let point = FractionalHex::new(1.7, -0.2);
+
+    assert_eq!(Hex::new(1, -1), point.round_with(RoundMode::Floor));
+    assert_eq!(Hex::new(2, 0), point.round_with(RoundMode::Ceil));
+
+    assert_float_eq!(
+        0.5,
+        FractionalHex::new(3.0, -1.0).edge_distance(),
+        abs <= 1e-12
+    );
+    assert_float_eq!(
+        0.0,
+        FractionalHex::new(0.5, 0.0).edge_distance(),
+        abs <= 1e-12
+    );
+    assert_float_eq!(
+        0.25,
+        FractionalHex::new(0.25, 0.0).edge_distance(),
+        abs <= 1e-12
+    );
+}
+
+#[test]
+fn test_line_between() {
+    // Between hex centers the steps of `line` come in order, with the hexes
+    // whose corners the segment cuts in between.
+    for a in Hex::new(0, 0).range(4) {
+        for b in Hex::new(1, -1).range(4) {
+            if b.q() - a.q() == b.r() - a.r() {
+                continue;
+            }
+            let between = HexLine::between(a.into(), b.into());
+            let mut rest = between.iter();
+            assert!(a.line(b).iter().all(|step| rest.any(|hex| hex == step)));
+        }
+    }
+
+    // Scattered fractional end points, including the far side of the grid.
+    let points: Vec<FractionalHex> = (0..40)
+        .map(|i| {
+            let i = i as f64;
+            FractionalHex::new(
+                (i * 0.618_034).fract() * 12.0 - 6.0,
+                (i * 0.414_214).fract() * 12.0 - 6.0,
+            )
+        })
+        .collect();
+    for a in points.iter() {
+        for b in points.iter() {
+            let line = HexLine::between(*a, *b);
+            assert_eq!(a.round(), line[0]);
+            assert_eq!(b.round(), *line.last().unwrap());
+            assert!(line.windows(2).all(|w| w[0].distance(w[1]) == 1));
+        }
+    }
+
+    let a = FractionalHex::new(1.943, 0.565);
+    let b = FractionalHex::new(6.449, -4.334);
+    assert_eq!(8, HexLine::between(a, b).len());
+
+    let layout = Layout {
+        orientation: LAYOUT_ORIENTATION_POINTY,
+        size: Point { x: 10.0, y: 10.0 },
+        origin: Point { x: 0.0, y: 0.0 },
+    };
+    let a = Hex::new(-2, 3);
+    let b = Hex::new(4, -4);
+    let from = LayoutTool::hex_to_pixel(layout, a);
+    let to = LayoutTool::hex_to_pixel(layout, b);
+    assert_eq!(
+        HexLine::between(a.into(), b.into()),
+        HexLine::pixels(layout, from, to)
+    );
+}
+
+#[test]
+fn test_line_supercover() {
+    // Running exactly between two hexes touches both of them.
+    let diagonal = HexLine::supercover(Hex::new(0, 0).into(), Hex::new(2, -1).into());
+    assert_eq!(
+        vec![
+            Hex::new(0, 0),
+            Hex::new(1, -1),
+            Hex::new(1, 0),
+            Hex::new(2, -1)
+        ],
+        diagonal
+    );
+
+    let straight = HexLine::supercover(Hex::new(0, 0).into(), Hex::new(3, 0).into());
+    assert_eq!(Hex::new(0, 0).line(Hex::new(3, 0)), straight);
+
+    let pairs = [
+        (Hex::new(0, 0), Hex::new(5, -2)),
+        (Hex::new(-3, 1), Hex::new(4, 2)),
+        (Hex::new(2, 2), Hex::new(-4, 1)),
+    ];
+    for (a, b) in pairs.iter() {
+        let forward = HexLine::supercover((*a).into(), (*b).into());
+        let backward = HexLine::supercover((*b).into(), (*a).into());
+        assert_eq!(
+            forward.iter().collect::<HashSet<_>>(),
+            backward.iter().collect::<HashSet<_>>()
+        );
+        for hex in a.line(*b) {
+            assert!(forward.contains(&hex));
+        }
+        assert_eq!(*a, forward[0]);
+        assert_eq!(b, forward.last().unwrap());
+    }
+}
+
+#[test]
+fn test_line_thick() {
+    let a = Hex::new(0, 0);
+    let b = Hex::new(4, 0);
+
+    let widths: Vec<HashSet<Hex>> = (1..=4)
+        .map(|width| HexLine::thick(a, b, width).into_iter().collect())
+        .collect();
+    assert_eq!(a.line(b), HexLine::thick(a, b, 1));
+    assert_eq!(
+        vec![5, 11, 19, 28],
+        widths.iter().map(|hexes| hexes.len()).collect::<Vec<_>>()
+    );
+    assert!(widths.windows(2).all(|w| w[0].is_subset(&w[1])));
+
+    // Even widths add their last row on the right, odd widths on the left.
+    assert!(widths[1].contains(&Hex::new(2, 1)));
+    assert!(!widths[1].contains(&Hex::new(2, -1)));
+    assert!(widths[2].contains(&Hex::new(2, -1)));
+    assert!(widths[2].contains(&Hex::new(-1, 0)));
+    assert!(!widths[2].contains(&Hex::new(-2, 0)));
+    assert!(!widths[2].contains(&Hex::new(2, -2)));
+    assert!(widths[3].contains(&Hex::new(1, 2)));
+    assert!(!widths[3].contains(&Hex::new(2, -2)));
+
+    assert_eq!(
+        widths[2],
+        HexLine::thick(b, a, 3).into_iter().collect::<HashSet<_>>()
+    );
+}
+
+#[test]
+fn test_line_integer() {
+    let origin = Hex::new(0, 0);
+    assert_eq!(vec![origin], HexLine::integer(origin, origin));
+
+    let hexes: Vec<Hex> = Hex::new(1, -2).range(7);
+    for a in hexes.iter().step_by(5) {
+        for b in hexes.iter() {
+            let float: Vec<Hex> = a.line(*b);
+            let integer: Vec<Hex> = HexLine::integer(*a, *b);
+            assert_eq!(float.len(), integer.len());
+            assert!(integer.windows(2).all(|w| w[0].distance(w[1]) == 1));
+
+            // Steps may only differ on a corner where q and r are both halfway.
+            let n: i32 = a.distance(*b);
+            for i in 0..float.len() {
+                if float[i] != integer[i] {
+                    let q: i32 = a.q() * n + (b.q() - a.q()) * i as i32;
+                    let r: i32 = a.r() * n + (b.r() - a.r()) * i as i32;
+                    assert_eq!(n, (2 * q).rem_euclid(2 * n));
+                    assert_eq!(n, (2 * r).rem_euclid(2 * n));
+                }
+            }
+        }
+    }
+
+    let far = Hex::new(1000, -377);
+    assert_eq!(origin.line(far), HexLine::integer(origin, far));
+    assert_eq!(
+        Hex::new(0, 0).line(Hex::new(1, -5)),
+        HexLine::integer(Hex::new(0, 0), Hex::new(1, -5))
+    );
+}
+
+#[test]
+fn test_fixed_arithmetic() {
+    let three: Fixed = FIXED_SQRT_3 * FIXED_SQRT_3;
+    assert!((three - Fixed::from_int(3)).abs() <= Fixed::from_bits(2));
+    assert_eq!(Fixed::from_bits(1_431_655_765), Fixed::from_ratio(1, 3));
+    assert_eq!(Fixed::from_bits(-2_863_311_531), Fixed::from_ratio(-2, 3));
+    assert_eq!(
+        Fixed::from_int(3) / Fixed::from_int(2),
+        Fixed::from_ratio(3, 2)
+    );
+
+    assert_eq!(3, Fixed::from_ratio(5, 2).round());
+    assert_eq!(-3, Fixed::from_ratio(-5, 2).round());
+    assert_eq!(-2, Fixed::from_ratio(-7, 4).round());
+    assert_eq!(1.25, Fixed::from_f64(1.25).to_f64());
+}
+
+#[test]
+fn test_fixed_layout() {
+    for orientation in [FIXED_ORIENTATION_POINTY, FIXED_ORIENTATION_FLAT].iter() {
+        let layout = FixedLayout {
+            orientation: *orientation,
+            size: FixedPoint {
+                x: Fixed::from_int(10),
+                y: Fixed::from_ratio(29, 2),
+            },
+            origin: FixedPoint {
+                x: Fixed::from_int(-7),
+                y: Fixed::from_ratio(1, 3),
+            },
+        };
+        for hex in Hex::new(3, -8).range(20) {
+            let pixel: FixedPoint = FixedLayoutTool::hex_to_pixel(layout, hex);
+            assert_eq!(hex, FixedLayoutTool::pixel_to_hex(layout, pixel).round());
+        }
+    }
+
+    let layout = FixedLayout {
+        orientation: FIXED_ORIENTATION_POINTY,
+        size: FixedPoint {
+            x: Fixed::ONE,
+            y: Fixed::ONE,
+        },
+        origin: FixedPoint::default(),
+    };
+    let pixel: FixedPoint = FixedLayoutTool::hex_to_pixel(layout, Hex::new(1, 0));
+    assert_eq!(FIXED_SQRT_3, pixel.x);
+    assert_eq!(Fixed::ZERO, pixel.y);
+    let pixel: FixedPoint = FixedLayoutTool::hex_to_pixel(layout, Hex::new(-3, 2));
+    assert_eq!(Fixed::from_bits(-14_878_203_148), pixel.x);
+    assert_eq!(Fixed::from_int(3), pixel.y);
+}
+
+#[test]
+fn test_area_cone() {
+    let origin = Hex::new(2, -1);
+    let cone: HashSet<Hex> = HexArea::cone(origin, 0, 2, PI / 3.0).collect();
+    let expected: HashSet<Hex> = [(1, 0), (2, 0), (2, -1), (1, 1)]
+        .iter()
+        .map(|(q, r)| origin.add(Hex::new(*q, *r)))
+        .collect();
+    assert_eq!(expected, cone);
+
+    for direction in 0..6 {
+        let cone: Vec<Hex> = HexArea::cone(origin, direction, 5, PI / 3.0).collect();
+        assert_eq!(1 + 3 + 3 + 5 + 5, cone.len());
+        assert!(cone
+            .iter()
+            .all(|hex| (1..=5).contains(&origin.distance(*hex))));
+    }
+    assert_eq!(90, HexArea::cone(origin, 2, 5, 2.0 * PI).count());
+
+    let layout = Layout {
+        orientation: LAYOUT_ORIENTATION_POINTY,
+        size: Point { x: 10.0, y: 10.0 },
+        origin: Point { x: 3.0, y: 4.0 },
+    };
+    let pixel: HashSet<Hex> = HexArea::cone_pixel(layout, origin, 0.0, 4, PI / 3.0).collect();
+    assert_eq!(
+        HexArea::cone(origin, 0, 4, PI / 3.0).collect::<HashSet<_>>(),
+        pixel
+    );
+}
+
+#[test]
+fn test_area_wedge() {
+    let origin = Hex::new(0, 0);
+    let wedge: HashSet<Hex> = HexArea::wedge(origin, 0, 1, 2).collect();
+    let expected: HashSet<Hex> = [(1, 0), (1, -1), (2, 0), (2, -1), (2, -2)]
+        .iter()
+        .map(|(q, r)| Hex::new(*q, *r))
+        .collect();
+    assert_eq!(expected, wedge);
+
+    let ray: Vec<Hex> = HexArea::wedge(origin, 4, 4, 3).collect();
+    assert_eq!(vec![Hex::new(-3, 3), Hex::new(-2, 2), Hex::new(-1, 1)], ray);
+
+    let wrapped: HashSet<Hex> = HexArea::wedge(origin, 5, 1, 3).collect();
+    assert_eq!(3 + 2 * 6, wrapped.len());
+    assert!(wrapped.contains(&Hex::new(3, 0)));
+    assert!(!wrapped.contains(&Hex::new(-1, 0)));
+}
+
+#[test]
+fn test_direction_sector() {
+    let from = Hex::new(3, -2);
+    assert_eq!(None, HexDirection::sector(from, from));
+    assert_eq!(None, HexDirection::nearest(from, from));
+
+    for direction in 0..6 {
+        let far = from.add(HexDirection::direction(direction).scale(4));
+        assert_eq!(
+            Some(HexSector::Direction(direction)),
+            HexDirection::sector(from, far)
+        );
+
+        let diagonal = HexDirection::diagonal_neighbor(from, direction);
+        assert_eq!(
+            Some(HexSector::Diagonal(direction)),
+            HexDirection::sector(from, diagonal)
+        );
+        assert_eq!(Some(direction), HexDirection::nearest(from, diagonal));
+    }
+
+    assert_eq!(
+        Some(HexSector::Direction(0)),
+        HexDirection::sector(from, from.add(Hex::new(3, -1)))
+    );
+    assert_eq!(
+        Some(1),
+        HexDirection::nearest(from, from.add(Hex::new(3, -2)))
+    );
+}
+
+#[test]
+fn test_direction_angle() {
+    let from = Hex::new(1, 1);
+    let pointy = Layout {
+        orientation: LAYOUT_ORIENTATION_POINTY,
+        size: Point { x: 10.0, y: 10.0 },
+        origin: Point { x: 5.0, y: 5.0 },
+    };
+    let flat = Layout {
+        orientation: LAYOUT_ORIENTATION_FLAT,
+        ..pointy
+    };
+
+    let east = HexDirection::neighbor(from, 0);
+    assert_float_eq!(0.0, HexDirection::angle(pointy, from, east), abs <= 1e-12);
+    assert_float_eq!(
+        PI / 6.0,
+        HexDirection::angle(flat, from, east),
+        abs <= 1e-12
+    );
+
+    let north = HexDirection::neighbor(from, 2);
+    assert_float_eq!(
+        -2.0 * PI / 3.0,
+        HexDirection::angle(pointy, from, north),
+        abs <= 1e-12
+    );
+    assert_float_eq!(
+        -PI / 2.0,
+        HexDirection::angle(flat, from, north),
+        abs <= 1e-12
+    );
+}
+
+#[test]
+fn test_layout_transform() {
+    let transforms = [
+        LAYOUT_TRANSFORM_ISOMETRIC,
+        LAYOUT_TRANSFORM_NONE
+            .with_gap(2.0)
+            .with_rotation(0.3)
+            .with_tilt(0.6),
+        LAYOUT_TRANSFORM_NONE.with_gap(4.0),
+    ];
+
+    for orientation in [LAYOUT_ORIENTATION_POINTY, LAYOUT_ORIENTATION_FLAT].iter() {
+        for transform in transforms.iter() {
+            let layout = Layout::new(
+                *orientation,
+                Point { x: 10.0, y: 12.0 },
+                Point { x: 40.0, y: -7.0 },
+            )
+            .with_transform(*transform);
+
+            for hex in Hex::new(-2, 5).range(6) {
+                let center: Point = LayoutTool::hex_to_pixel(layout, hex);
+                let back: FractionalHex = LayoutTool::pixel_to_hex(layout, center);
+                assert_float_eq!(hex.q() as f64, back.q(), abs <= 1e-9);
+                assert_float_eq!(hex.r() as f64, back.r(), abs <= 1e-9);
+
+                for corner in LayoutTool::polygon_corners(layout, hex) {
+                    let inside: Point = Point {
+                        x: center.x + (corner.x - center.x) * 0.9,
+                        y: center.y + (corner.y - center.y) * 0.9,
+                    };
+                    assert_eq!(hex, LayoutTool::pixel_to_hex(layout, inside).round());
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_layout_gap() {
+    let layout = Layout::new(
+        LAYOUT_ORIENTATION_POINTY,
+        Point { x: 10.0, y: 10.0 },
+        Point { x: 0.0, y: 0.0 },
+    )
+    .with_transform(LAYOUT_TRANSFORM_NONE.with_gap(3.0));
+
+    for direction in 0..6 {
+        let center: Point = LayoutTool::hex_to_pixel(layout, HexDirection::direction(direction));
+        let spacing: f64 = (center.x * center.x + center.y * center.y).sqrt();
+        assert_float_eq!(10.0 * 3f64.sqrt() + 3.0, spacing, abs <= 1e-9);
+    }
+
+    let corner: Point = LayoutTool::corner_offset(layout, 0);
+    assert_float_eq!(
+        10.0,
+        (corner.x * corner.x + corner.y * corner.y).sqrt(),
+        abs <= 1e-9
+    );
+
+    let plain = Layout::new(
+        LAYOUT_ORIENTATION_FLAT,
+        Point { x: 10.0, y: 15.0 },
+        Point { x: 35.0, y: 71.0 },
+    );
+    let point: Point = LayoutTool::hex_to_pixel(plain, Hex::new(3, 4));
+    let o = LAYOUT_ORIENTATION_FLAT;
+    let expected: Point = Point {
+        x: (o.f0 * 3.0 + o.f1 * 4.0) * 10.0 + 35.0,
+        y: (o.f2 * 3.0 + o.f3 * 4.0) * 15.0 + 71.0,
+    };
+    assert_eq!(expected, point);
+}
+
+#[test]
+fn test_layout_y_up() {
+    let down = Layout::new(
+        LAYOUT_ORIENTATION_POINTY,
+        Point { x: 10.0, y: 12.0 },
+        Point { x: 5.0, y: 20.0 },
+    );
+    let up = down.with_transform(LAYOUT_TRANSFORM_NONE.with_axis(AxisConvention::YUp));
+
+    let hex = Hex::new(2, 3);
+    let a: Point = LayoutTool::hex_to_pixel(down, hex);
+    let b: Point = LayoutTool::hex_to_pixel(up, hex);
+    assert_eq!(a.x, b.x);
+    assert_eq!(a.y - 20.0, 20.0 - b.y);
+    assert_eq!(hex, LayoutTool::pixel_to_hex(up, b).round());
+
+    let corners_down: Vec<Point> = LayoutTool::polygon_corners(down, hex);
+    let corners_up: Vec<Point> = LayoutTool::polygon_corners(up, hex);
+    for (c, d) in corners_down.iter().zip(corners_up.iter()) {
+        assert_float_eq!(c.y - a.y, b.y - d.y, abs <= 1e-9);
+    }
+    let area = |ring: &[Point]| -> f64 {
+        (0..6)
+            .map(|i| ring[i].x * ring[(i + 1) % 6].y - ring[(i + 1) % 6].x * ring[i].y)
+            .sum()
+    };
+    assert!(area(&corners_down) < 0.0);
+    assert!(area(&corners_up) > 0.0);
+    assert_eq!(
+        HexRegion::edge_directions(down),
+        HexRegion::edge_directions(up)
+    );
+
+    let file: MapFile<u8> = MapFile {
+        layout: up,
+        shape: MapShape::Sparse,
+        schema_version: 1,
+        tiles: [(hex, 7u8)].iter().copied().collect(),
+    };
+    let mut bytes: Vec<u8> = vec![];
+    file.write(&mut bytes, false).unwrap();
+    assert_eq!(file, MapFile::read(&mut bytes.as_slice()).unwrap());
+}
+
+#[test]
+fn test_cached_layout() {
+    let layouts: [TransformedLayout; 3] = [
+        Layout::new(
+            LAYOUT_ORIENTATION_POINTY,
+            Point { x: 10.0, y: 12.0 },
+            Point { x: 5.0, y: 20.0 },
+        )
+        .into(),
+        Layout::new(
+            LAYOUT_ORIENTATION_FLAT,
+            Point { x: 8.0, y: 8.0 },
+            Point { x: -3.0, y: 1.5 },
+        )
+        .with_transform(LAYOUT_TRANSFORM_ISOMETRIC),
+        Layout::new(
+            LAYOUT_ORIENTATION_POINTY,
+            Point { x: 9.0, y: 7.0 },
+            Point { x: 40.0, y: -12.0 },
+        )
+        .with_transform(LayoutTransform::new(2.5, -1.1, 0.9, AxisConvention::YUp)),
+    ];
+
+    for layout in layouts.iter() {
+        let cached = CachedLayout::new(*layout);
+        assert_eq!(*layout, cached.layout());
+
+        let hexes: Vec<Hex> = Hex::new(1, -1).range(3);
+        let mut buffer: Vec<Point> = vec![Point { x: 0.0, y: 0.0 }; hexes.len() * 6];
+        cached.polygon_corners_into(&hexes, &mut buffer);
+
+        for (hex, corners) in hexes.iter().zip(buffer.chunks(6)) {
+            let expected: Vec<Point> = LayoutTool::polygon_corners(*layout, *hex);
+            assert_eq!(expected, cached.polygon_corners(*hex).to_vec());
+            assert_eq!(expected, corners.to_vec());
+        }
+    }
+}
+
+#[test]
+fn test_batch_layout() {
+    let layouts: [TransformedLayout; 2] = [
+        Layout::new(
+            LAYOUT_ORIENTATION_POINTY,
+            Point { x: 10.0, y: 12.0 },
+            Point { x: 5.0, y: 20.0 },
+        )
+        .into(),
+        Layout::new(
+            LAYOUT_ORIENTATION_FLAT,
+            Point { x: 7.0, y: 9.0 },
+            Point { x: -3.0, y: 1.5 },
+        )
+        .with_transform(LayoutTransform::new(1.5, 0.7, 0.8, AxisConvention::YUp)),
+    ];
+    let hexes: Vec<Hex> = Hex::new(4, -9).range(5);
+    let q: Vec<i32> = hexes.iter().map(|hex| hex.q()).collect();
+    let r: Vec<i32> = hexes.iter().map(|hex| hex.r()).collect();
+    assert_eq!(1, hexes.len() % 2);
+
+    for layout in layouts.iter() {
+        let mut x: Vec<f64> = vec![0.0; hexes.len()];
+        let mut y: Vec<f64> = vec![0.0; hexes.len()];
+        LayoutTool::hex_to_pixel_batch(*layout, &q, &r, &mut x, &mut y);
+
+        let mut fq: Vec<f64> = vec![0.0; hexes.len()];
+        let mut fr: Vec<f64> = vec![0.0; hexes.len()];
+        let shifted: Vec<f64> = x.iter().map(|x| x + 2.9).collect();
+        LayoutTool::pixel_to_hex_batch(*layout, &shifted, &y, &mut fq, &mut fr);
+
+        for (i, hex) in hexes.iter().enumerate() {
+            let pixel: Point = LayoutTool::hex_to_pixel(*layout, *hex);
+            assert_eq!(pixel.x.to_bits(), x[i].to_bits());
+            assert_eq!(pixel.y.to_bits(), y[i].to_bits());
+
+            let back = LayoutTool::pixel_to_hex(
+                *layout,
+                Point {
+                    x: shifted[i],
+                    y: y[i],
+                },
+            );
+            assert_eq!(back.q().to_bits(), fq[i].to_bits());
+            assert_eq!(back.r().to_bits(), fr[i].to_bits());
+        }
+    }
+}
+
+#[test]
+fn test_batch_round() {
+    let mut q: Vec<f64> = vec![0.5, -0.5, 2.5, -2.5, 0.49999999999999994, -0.0, 1e6 + 0.5];
+    let mut r: Vec<f64> = vec![0.0, 0.5, -1.5, 1.5, -0.25, 0.0, -1e6];
+    for i in 0..200 {
+        q.push((i as f64 * 0.37).sin() * 40.0);
+        r.push((i as f64 * 0.53).cos() * 40.0);
+        q.push(i as f64 / 4.0 - 25.0);
+        r.push(12.5 - i as f64 / 8.0);
+    }
+
+    let mut out_q: Vec<i32> = vec![0; q.len()];
+    let mut out_r: Vec<i32> = vec![0; q.len()];
+    FractionalHex::round_batch(&q, &r, &mut out_q, &mut out_r);
+
+    for i in 0..q.len() {
+        let expected: Hex = FractionalHex::new(q[i], r[i]).round();
+        assert_eq!(expected, Hex::new(out_q[i], out_r[i]));
+    }
+}
+
+#[test]
+fn test_pick() {
+    let layout = Layout::new(
+        LAYOUT_ORIENTATION_POINTY,
+        Point { x: 20.0, y: 20.0 },
+        Point { x: 100.0, y: 50.0 },
+    );
+    let hex = Hex::new(2, -1);
+    let center: Point = LayoutTool::hex_to_pixel(layout, hex);
+    let corners: Vec<Point> = LayoutTool::polygon_corners(layout, hex);
+    let toward = |target: Point, amount: f64| -> Point {
+        Point {
+            x: target.x + (center.x - target.x) * amount,
+            y: target.y + (center.y - target.y) * amount,
+        }
+    };
+
+    let pick = HexPicker::pick(layout, center, 3.0);
+    assert_eq!(hex, pick.hex);
+    assert_eq!(PickTarget::Center, pick.target);
+    assert_float_eq!(10.0 * 3f64.sqrt(), pick.edge_distance, abs <= 1e-9);
+    assert_float_eq!(20.0, pick.corner_distance, abs <= 1e-9);
+
+    let pick = HexPicker::pick(layout, toward(corners[4], 0.05), 3.0);
+    assert_eq!(hex, pick.hex);
+    assert_eq!(PickTarget::Corner(4), pick.target);
+    assert_float_eq!(1.0, pick.corner_distance, abs <= 1e-9);
+
+    let middle = Point {
+        x: (corners[1].x + corners[2].x) / 2.0,
+        y: (corners[1].y + corners[2].y) / 2.0,
+    };
+    let pick = HexPicker::pick(layout, toward(middle, 0.1), 3.0);
+    assert_eq!(hex, pick.hex);
+    assert_eq!(PickTarget::Edge(1), pick.target);
+    assert_eq!(1, pick.edge);
+    assert_float_eq!(3f64.sqrt(), pick.edge_distance, abs <= 1e-9);
+    assert_eq!(
+        PickTarget::Center,
+        HexPicker::pick(layout, toward(middle, 0.1), 1.0).target
+    );
+}
+
+#[test]
+fn test_path_follower() {
+    let layout = Layout::new(
+        LAYOUT_ORIENTATION_POINTY,
+        Point { x: 10.0, y: 10.0 },
+        Point { x: 0.0, y: 0.0 },
+    );
+    let step: f64 = 10.0 * 3f64.sqrt();
+    let path: Vec<Hex> = Hex::new(0, 0).line(Hex::new(3, 0));
+
+    let linear = PathFollower::new(layout, &path, step, Easing::Linear, false);
+    assert_float_eq!(3.0, linear.duration(), abs <= 1e-9);
+    let sample = linear.sample(1.5);
+    assert_float_eq!(1.5 * step, sample.position.x, abs <= 1e-9);
+    assert_float_eq!(0.0, sample.position.y, abs <= 1e-9);
+    assert_float_eq!(0.0, sample.facing, abs <= 1e-9);
+    assert!(!sample.finished);
+    assert!(linear.sample(5.0).finished);
+    assert_eq!(
+        LayoutTool::hex_to_pixel(layout, path[3]),
+        linear.sample(5.0).position
+    );
+
+    let eased = PathFollower::new(layout, &path, step, Easing::EaseIn, false);
+    assert_float_eq!(0.75 * step, eased.sample(1.5).position.x, abs <= 1e-9);
+
+    let single = PathFollower::new(layout, &path[..1], step, Easing::Linear, true);
+    assert_eq!(0.0, single.duration());
+    assert!(single.sample(0.0).finished);
+}
+
+#[test]
+fn test_path_follower_smooth() {
+    let layout = Layout::new(
+        LAYOUT_ORIENTATION_FLAT,
+        Point { x: 10.0, y: 10.0 },
+        Point { x: 0.0, y: 0.0 },
+    );
+    let path: Vec<Hex> = vec![
+        Hex::new(0, 0),
+        Hex::new(1, 0),
+        Hex::new(1, 1),
+        Hex::new(1, 2),
+        Hex::new(2, 2),
+    ];
+    let step: f64 = 10.0 * 3f64.sqrt();
+    let smooth = PathFollower::new(layout, &path, step, Easing::Linear, true);
+    let plain = PathFollower::new(layout, &path, step, Easing::Linear, false);
+
+    for (i, hex) in path.iter().enumerate() {
+        let center: Point = LayoutTool::hex_to_pixel(layout, *hex);
+        let sample = smooth.sample(i as f64);
+        assert_float_eq!(center.x, sample.position.x, abs <= 1e-9);
+        assert_float_eq!(center.y, sample.position.y, abs <= 1e-9);
+    }
+
+    // Through the corner the spline turns gradually and leaves the straight path.
+    let corner_smooth = smooth.sample(1.5);
+    let corner_plain = plain.sample(1.5);
+    assert!((corner_smooth.position.x - corner_plain.position.x).abs() > 0.1);
+    let before: f64 = smooth.sample(0.9).facing;
+    let at: f64 = smooth.sample(1.0).facing;
+    let after: f64 = smooth.sample(1.1).facing;
+    assert!((before < at && at < after) || (before > at && at > after));
+}
+
+#[test]
+fn test_fog_of_war() {
+    let mut fog = FogOfWar::new();
+    let origin = Hex::new(0, 0);
+    assert_eq!(Visibility::Unexplored, fog.visibility(origin));
+
+    let changes = fog.set_viewer(0, origin, 2, |_| false);
+    assert_eq!(19, changes.len());
+    assert!(changes.iter().all(|(_, v)| *v == Visibility::Visible));
+
+    let east = Hex::new(1, 0);
+    let changes = fog.set_viewer(0, east, 2, |_| false);
+    assert_eq!(10, changes.len());
+    assert!(changes.contains(&(Hex::new(-2, 0), Visibility::Explored)));
+    assert!(changes.contains(&(Hex::new(3, 0), Visibility::Visible)));
+    assert_eq!(Visibility::Visible, fog.visibility(origin));
+
+    assert!(fog.set_viewer(0, east, 2, |_| false).is_empty());
+
+    fog.set_viewer(1, Hex::new(-3, 0), 1, |_| false);
+    let changes = fog.remove_viewer(0);
+    assert!(!changes.iter().any(|(hex, _)| *hex == Hex::new(-2, 0)));
+    assert!(changes.iter().all(|(_, v)| *v == Visibility::Explored));
+    assert_eq!(Visibility::Visible, fog.visibility(Hex::new(-2, 0)));
+    assert_eq!(Visibility::Explored, fog.visibility(east));
+    assert_eq!(7, fog.visible().count());
+    assert_eq!(19 + 5 + 6, fog.explored().len());
+}
+
+#[test]
+fn test_field_of_view() {
+    let origin = Hex::new(0, 0);
+    let wall = Hex::new(1, 0);
+    let seen: HashSet<Hex> = FieldOfView::visible(origin, 3, |hex| hex == wall)
+        .into_iter()
+        .collect();
+
+    assert!(seen.contains(&origin));
+    assert!(seen.contains(&wall));
+    assert!(seen.contains(&Hex::new(0, 3)));
+    let hidden: HashSet<Hex> = origin
+        .range(3)
+        .into_iter()
+        .filter(|hex| !seen.contains(hex))
+        .collect();
+    let shadow: HashSet<Hex> = [(2, 0), (3, 0), (2, -1), (3, -1), (1, 1), (2, 1)]
+        .iter()
+        .map(|(q, r)| Hex::new(*q, *r))
+        .collect();
+    assert_eq!(shadow, hidden);
+}
+
+#[test]
+fn test_map_file_corrupted() {
+    let rectangle: MapFile<u8> = MapFile {
+        layout: Layout::new(
+            LAYOUT_ORIENTATION_POINTY,
+            Point { x: 1.0, y: 1.0 },
+            Point { x: 0.0, y: 0.0 },
+        )
+        .into(),
+        shape: MapShape::Rectangle {
+            axis: OffsetAxis::Q,
+            offset: HEX_EVEN,
+            columns: 3,
+            rows: 2,
+        },
+        schema_version: 1,
+        tiles: [(Hex::new(0, 0), 4u8)].iter().copied().collect(),
+    };
+    let mut valid: Vec<u8> = vec![];
+    rectangle.write(&mut valid, false).unwrap();
+
+    // Header of 11 bytes and a layout of 129, then the shape.
+    let shape: usize = 11 + 129;
+    let corrupt = |at: usize, value: &[u8]| -> io::ErrorKind {
+        let mut bytes: Vec<u8> = valid.clone();
+        bytes[at..at + value.len()].copy_from_slice(value);
+        MapFile::<u8>::read(&mut bytes.as_slice())
+            .unwrap_err()
+            .kind()
+    };
+    assert_eq!(io::ErrorKind::InvalidData, corrupt(10, &[2]));
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        corrupt(shape + 2, &3i32.to_le_bytes())
+    );
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        corrupt(shape + 6, &(-1i32).to_le_bytes())
+    );
+    let huge: [u8; 4] = 70_000i32.to_le_bytes();
+    assert_eq!(
+        io::ErrorKind::InvalidData,
+        corrupt(shape + 6, &[huge, huge].concat())
+    );
+
+    let mut trailing: Vec<u8> = valid.clone();
+    trailing.push(0);
+    let error: io::Error = MapFile::<u8>::read(&mut trailing.as_slice()).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, error.kind());
+
+    let hexagon: MapFile<u8> = MapFile {
+        shape: MapShape::Hexagon {
+            center: Hex::new(0, 0),
+            radius: 1,
+        },
+        ..rectangle.clone()
+    };
+    let mut valid: Vec<u8> = vec![];
+    hexagon.write(&mut valid, true).unwrap();
+    let corrupt = |at: usize, value: i32| -> io::ErrorKind {
+        let mut bytes: Vec<u8> = valid.clone();
+        bytes[at..at + 4].copy_from_slice(&value.to_le_bytes());
+        MapFile::<u8>::read(&mut bytes.as_slice())
+            .unwrap_err()
+            .kind()
+    };
+    assert_eq!(io::ErrorKind::InvalidData, corrupt(shape + 9, -1));
+    assert_eq!(io::ErrorKind::InvalidData, corrupt(shape + 9, i32::MAX));
+    assert_eq!(io::ErrorKind::InvalidData, corrupt(shape + 1, i32::MIN));
+
+    let sparse: MapFile<u8> = MapFile {
+        shape: MapShape::Sparse,
+        ..rectangle.clone()
+    };
+    let mut bytes: Vec<u8> = vec![];
+    sparse.write(&mut bytes, false).unwrap();
+    bytes[shape + 5..shape + 9].copy_from_slice(&i32::MAX.to_le_bytes());
+    bytes[shape + 9..shape + 13].copy_from_slice(&2i32.to_le_bytes());
+    let error: io::Error = MapFile::<u8>::read(&mut bytes.as_slice()).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, error.kind());
+
+    let too_large: MapFile<u8> = MapFile {
+        shape: MapShape::Rectangle {
+            axis: OffsetAxis::Q,
+            offset: HEX_EVEN,
+            columns: 70_000,
+            rows: 70_000,
+        },
+        ..rectangle
+    };
+    assert!(too_large.write(&mut vec![], false).is_err());
+}
+
+#[test]
+fn test_tiled_malformed() {
+    let map = TiledMap {
+        axis: OffsetAxis::R,
+        offset: HEX_EVEN,
+        width: 2,
+        height: 2,
+        tile_width: 28,
+        tile_height: 24,
+        hex_side_length: 14,
+        layers: vec![TiledLayer {
+            name: "é".to_string(),
+            tiles: [(Hex::new(0, 0), 5)].iter().copied().collect(),
+        }],
+    };
+    let tmx: String = map.to_tmx();
+    let json: String = map.to_json();
+
+    let invalid = |result: io::Result<TiledMap>| -> bool {
+        result.err().map(|error| error.kind()) == Some(io::ErrorKind::InvalidData)
+    };
+    assert!(invalid(TiledMap::from_tmx(
+        &tmx.replace("width=\"2\"", "foo=é")
+    )));
+    assert!(invalid(TiledMap::from_tmx(
+        &tmx.replace("width=\"2\"", "width=2")
+    )));
+    assert!(invalid(TiledMap::from_tmx(&tmx.replace(
+        "width=\"2\" height=\"2\"",
+        "width=\"70000\" height=\"70000\""
+    ))));
+    assert!(invalid(TiledMap::from_tmx(
+        &tmx.replace("height=\"2\"", "height=\"-2\"")
+    )));
+    assert!(invalid(TiledMap::from_json(&json.replace("5,", "-5,"))));
+    assert!(invalid(TiledMap::from_json(&json.replace("5,", "5.5,"))));
+    assert!(invalid(TiledMap::from_json(&"[".repeat(100_000))));
+
+    // Cut or damaged files fail without panicking.
+    let damage: [&str; 6] = ["é", "\"", "'", "<", ">", "\\"];
+    for text in [tmx, json].iter() {
+        for (i, c) in text.char_indices() {
+            let _ = TiledMap::from_tmx(&text[..i]);
+            let _ = TiledMap::from_json(&text[..i]);
+            for replacement in damage.iter() {
+                let damaged: String =
+                    format!("{}{}{}", &text[..i], replacement, &text[i + c.len_utf8()..]);
+                let _ = TiledMap::from_tmx(&damaged);
+                let _ = TiledMap::from_json(&damaged);
+            }
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "rows must be even")]
+fn test_wrap_odd_size() {
+    // Odd sizes only break the seam across the shoved rows.
+    let cylinder = Wrap {
+        axis: OffsetAxis::R,
+        offset: HEX_ODD,
+        columns: Some(9),
+        rows: None,
+    };
+    assert_eq!(
+        Hex::new(0, 0),
+        WrapTool::neighbor(cylinder, Hex::new(8, 0), 0)
+    );
+
+    let torus = Wrap {
+        rows: Some(7),
+        ..cylinder
+    };
+    WrapTool::neighbor(torus, Hex::new(0, 0), 0);
+}
+
+#[test]
+fn test_path_follower_speed() {
+    let layout = Layout::new(
+        LAYOUT_ORIENTATION_POINTY,
+        Point { x: 10.0, y: 10.0 },
+        Point { x: 0.0, y: 0.0 },
+    );
+    let path: Vec<Hex> = Hex::new(0, 0).line(Hex::new(3, 0));
+
+    for speed in [0.0, -2.0, f64::NAN, f64::INFINITY].iter() {
+        let result = std::panic::catch_unwind(|| {
+            PathFollower::new(layout, &path, *speed, Easing::Linear, false)
+        });
+        assert!(result.is_err(), "speed {} was accepted", speed);
+    }
+}
+
+#[test]
+fn test_line_integer_matches_float() {
+    let hexes: Vec<Hex> = Hex::new(1, -2).range(7);
+    for a in hexes.iter() {
+        for b in hexes.iter() {
+            assert_eq!(a.line(*b), HexLine::integer(*a, *b));
+        }
+    }
+
+    // q and r both halfway, left to rounding error by the float version.
+    for b in [
+        Hex::new(3, 3),
+        Hex::new(-5, -5),
+        Hex::new(9, 11),
+        Hex::new(-7, 1),
+    ]
+    .iter()
+    {
+        let origin = Hex::new(0, 0);
+        assert_eq!(origin.line(*b), HexLine::integer(origin, *b));
+    }
+}
+
+#[test]
+fn test_flow_field_costs() {
+    // X(10) - A(1) - G(1): X pays for A and G, not for itself.
+    let x = Hex::new(0, 0);
+    let a = Hex::new(1, 0);
+    let g = Hex::new(2, 0);
+    let costs: HashMap<Hex, i32> = [(x, 10), (a, 1), (g, 1)].iter().copied().collect();
+    let field = FlowField::new(costs, &[g]);
+    assert_eq!(Some(0), field.distance(g));
+    assert_eq!(Some(1), field.distance(a));
+    assert_eq!(Some(2), field.distance(x));
+
+    // Two goals next to X, the cheap one to enter wins.
+    let costly = HexDirection::neighbor(x, 0);
+    let cheap = HexDirection::neighbor(x, 3);
+    let costs: HashMap<Hex, i32> = [(x, 1), (costly, 100), (cheap, 1)]
+        .iter()
+        .copied()
+        .collect();
+    let mut field = FlowField::new(costs.clone(), &[costly, cheap]);
+    assert_eq!(Some(1), field.distance(x));
+    assert_eq!(Some(3), field.direction(x));
+
+    assert_eq!(vec![x], field.set_cost(cheap, Some(200)));
+    assert_eq!(Some(100), field.distance(x));
+    assert_eq!(Some(0), field.direction(x));
+    assert!(field.set_cost(x, Some(7)).is_empty());
+
+    let mut costs: HashMap<Hex, i32> = Hex::new(0, 0)
+        .range(4)
+        .into_iter()
+        .map(|h| (h, 1 + (h.q() * 3 + h.r() * 5).rem_euclid(7)))
+        .collect();
+    let goals = [Hex::new(-3, 0), Hex::new(2, 2)];
+    let mut field = FlowField::new(costs.clone(), &goals);
+    let edits = [
+        (Hex::new(0, 0), Some(9)),
+        (Hex::new(-2, 0), None),
+        (Hex::new(1, 1), Some(1)),
+        (Hex::new(-3, 0), Some(4)),
+        (Hex::new(0, 0), Some(1)),
+        (Hex::new(-2, 0), Some(2)),
+        (Hex::new(2, 2), None),
+    ];
+    for (hex, cost) in edits.iter() {
+        field.set_cost(*hex, *cost);
+        match cost {
+            Some(cost) => costs.insert(*hex, *cost),
+            None => costs.remove(hex),
+        };
+        let rebuilt = FlowField::new(costs.clone(), &goals);
+        assert_eq!(rebuilt.distances(), field.distances());
+        for hex in costs.keys() {
+            let through: Option<i32> = (0..6)
+                .filter_map(|direction| {
+                    let next: Hex = HexDirection::neighbor(*hex, direction);
+                    Some(field.distance(next)? + costs.get(&next)?)
+                })
+                .min();
+            if !goals.contains(hex) {
+                assert_eq!(through, field.distance(*hex));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_gbt_invalid_index() {
+    assert_eq!(None, HexGbt::parent(u64::MAX));
+    assert_eq!(None, HexGbt::decode(u64::MAX));
+    assert!(HexGbt::children(u64::MAX).is_empty());
+
+    let index = HexGbt::encode(Hex::new(4, -3), 2).unwrap();
+    assert!(HexGbt::decode(index).is_some());
+    for position in 2..GBT_MAX_RESOLUTION {
+        let shift: u32 = 3 * (GBT_MAX_RESOLUTION - position - 1) as u32;
+        assert_eq!(None, HexGbt::decode(index & !(1 << shift)));
+    }
+}
+
+#[test]
+fn test_chunked_map_failed_load() {
+    let directory =
+        std::env::temp_dir().join(format!("hexagon_tiles_broken_{}", std::process::id()));
+    let storage = FileStorage {
+        directory: directory.clone(),
+    };
+    let mut map: ChunkedMap<u8, FileStorage> = ChunkedMap::new(storage.clone(), 2);
+    let hex = Hex::new(1, 0);
+
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(storage.path(map.chunk_of(hex)), [5, 0, 0, 0]).unwrap();
+    assert!(map.insert(hex, 1).is_err());
+    assert!(map.dirty_chunks().is_empty());
+    assert!(map.save().is_ok());
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,592 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::hexagon::{Hex, OffsetCoord};
+use crate::layout::{Layout, LAYOUT_ORIENTATION_FLAT, LAYOUT_ORIENTATION_POINTY};
+use crate::point::Point;
+use crate::tools::{HexOffset, OffsetAxis, HEX_EVEN, HEX_ODD};
+
+/// A tile layer of a Tiled map. Tiles hold the raw global tile ids including
+/// flip flags, empty cells (gid 0) are left out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledLayer {
+    pub name: String,
+    pub tiles: HashMap<Hex, u32>,
+}
+
+/// A hexagonal map of the Tiled editor.
+///
+/// `staggeraxis` x maps to `OffsetAxis::Q` with flat hexes, y to
+/// `OffsetAxis::R` with pointy hexes, and `staggerindex` odd or even to
+/// `HEX_ODD` or `HEX_EVEN`. Only finite maps with CSV encoded layers are
+/// supported. Tiles outside `width` x `height` are not exported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledMap {
+    pub axis: OffsetAxis,
+    pub offset: i32,
+    pub width: i32,
+    pub height: i32,
+    pub tile_width: i32,
+    pub tile_height: i32,
+    pub hex_side_length: i32,
+    pub layers: Vec<TiledLayer>,
+}
+
+impl TiledMap {
+    /// The layout matching Tiled's rendering, in map pixels with the top left
+    /// corner of the first tile at the origin.
+    pub fn layout(&self) -> Layout {
+        let width: f64 = self.tile_width as f64;
+        let height: f64 = self.tile_height as f64;
+        let side: f64 = self.hex_side_length as f64;
+        let shoved: f64 = if self.offset == HEX_EVEN { 1.0 } else { 0.0 };
+
+        match self.axis {
+            OffsetAxis::R => Layout {
+                orientation: LAYOUT_ORIENTATION_POINTY,
+                size: Point {
+                    x: width / LAYOUT_ORIENTATION_POINTY.f0,
+                    y: (height + side) / 3.0,
+                },
+                origin: Point {
+                    x: width / 2.0 * (1.0 + shoved),
+                    y: height / 2.0,
+                },
+            },
+            OffsetAxis::Q => Layout {
+                orientation: LAYOUT_ORIENTATION_FLAT,
+                size: Point {
+                    x: (width + side) / 3.0,
+                    y: height / LAYOUT_ORIENTATION_FLAT.f3,
+                },
+                origin: Point {
+                    x: width / 2.0,
+                    y: height / 2.0 * (1.0 + shoved),
+                },
+            },
+        }
+    }
+
+    pub fn from_tmx(text: &str) -> io::Result<TiledMap> {
+        let (map, rest) = xml_tag(text, "map")?.ok_or_else(|| invalid("missing map element"))?;
+        let mut tiled: TiledMap = Self::from_attributes(|name| map.get(name).cloned())?;
+
+        let mut remaining: &str = rest;
+        while let Some((layer, after)) = xml_tag(remaining, "layer")? {
+            let (data, after_data) =
+                xml_tag(after, "data")?.ok_or_else(|| invalid("layer without data"))?;
+            if data.get("encoding").map(String::as_str) != Some("csv") {
+                return Err(invalid("only csv layer data is supported"));
+            }
+            let end: usize = after_data
+                .find("</data>")
+                .ok_or_else(|| invalid("unterminated layer data"))?;
+
+            let gids: Vec<u32> = after_data[..end]
+                .split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse::<u32>().map_err(|_| invalid("bad tile id")))
+                .collect::<io::Result<Vec<u32>>>()?;
+
+            let name: String = layer.get("name").cloned().unwrap_or_default();
+            tiled.layers.push(tiled.layer_from_gids(name, &gids)?);
+            remaining = &after_data[end..];
+        }
+
+        Ok(tiled)
+    }
+
+    pub fn to_tmx(&self) -> String {
+        let mut out: String = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out += &format!(
+            "<map version=\"1.10\" orientation=\"hexagonal\" renderorder=\"right-down\" \
+             width=\"{}\" height=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" infinite=\"0\" \
+             hexsidelength=\"{}\" staggeraxis=\"{}\" staggerindex=\"{}\">\n",
+            self.width,
+            self.height,
+            self.tile_width,
+            self.tile_height,
+            self.hex_side_length,
+            self.stagger_axis(),
+            self.stagger_index()
+        );
+
+        for (id, layer) in self.layers.iter().enumerate() {
+            out += &format!(
+                " <layer id=\"{}\" name=\"{}\" width=\"{}\" height=\"{}\">\n  \
+                 <data encoding=\"csv\">\n",
+                id + 1,
+                escape(&layer.name),
+                self.width,
+                self.height
+            );
+            let rows: Vec<String> = self
+                .gid_rows(layer)
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(u32::to_string)
+                        .collect::<Vec<String>>()
+                        .join(",")
+                })
+                .collect();
+            out += &rows.join(",\n");
+            out += "\n</data>\n </layer>\n";
+        }
+
+        out += "</map>\n";
+        out
+    }
+
+    pub fn from_json(text: &str) -> io::Result<TiledMap> {
+        let root: Json = JsonParser::new(text).parse()?;
+        let mut tiled: TiledMap = Self::from_attributes(|name| match root.get(name) {
+            Some(Json::String(value)) => Some(value.clone()),
+            Some(Json::Number(value)) => Some(value.to_string()),
+            Some(Json::Bool(value)) => Some(value.to_string()),
+            _ => None,
+        })?;
+
+        let layers: &[Json] = match root.get("layers") {
+            Some(Json::Array(layers)) => layers,
+            _ => &[],
+        };
+        for layer in layers {
+            if layer.get("type") != Some(&Json::String("tilelayer".to_string())) {
+                continue;
+            }
+            let gids: Vec<u32> = match layer.get("data") {
+                Some(Json::Array(values)) => values
+                    .iter()
+                    .map(|value| match value {
+                        Json::Number(gid)
+                            if gid.fract() == 0.0 && *gid >= 0.0 && *gid <= u32::MAX as f64 =>
+                        {
+                            Ok(*gid as u32)
+                        }
+                        _ => Err(invalid("bad tile id")),
+                    })
+                    .collect::<io::Result<Vec<u32>>>()?,
+                _ => return Err(invalid("only csv layer data is supported")),
+            };
+            let name: String = match layer.get("name") {
+                Some(Json::String(name)) => name.clone(),
+                _ => String::new(),
+            };
+            tiled.layers.push(tiled.layer_from_gids(name, &gids)?);
+        }
+
+        Ok(tiled)
+    }
+
+    pub fn to_json(&self) -> String {
+        let layers: Vec<String> = self
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(id, layer)| {
+                let data: Vec<String> = self
+                    .gid_rows(layer)
+                    .concat()
+                    .iter()
+                    .map(u32::to_string)
+                    .collect();
+                format!(
+                    "{{\"id\":{},\"type\":\"tilelayer\",\"name\":\"{}\",\"x\":0,\"y\":0,\
+                     \"width\":{},\"height\":{},\"opacity\":1,\"visible\":true,\"data\":[{}]}}",
+                    id + 1,
+                    json_escape(&layer.name),
+                    self.width,
+                    self.height,
+                    data.join(",")
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"type\":\"map\",\"version\":\"1.10\",\"orientation\":\"hexagonal\",\
+             \"renderorder\":\"right-down\",\"width\":{},\"height\":{},\"tilewidth\":{},\
+             \"tileheight\":{},\"infinite\":false,\"hexsidelength\":{},\"staggeraxis\":\"{}\",\
+             \"staggerindex\":\"{}\",\"layers\":[{}]}}",
+            self.width,
+            self.height,
+            self.tile_width,
+            self.tile_height,
+            self.hex_side_length,
+            self.stagger_axis(),
+            self.stagger_index(),
+            layers.join(",")
+        )
+    }
+
+    fn from_attributes<F>(attribute: F) -> io::Result<TiledMap>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if attribute("orientation").as_deref() != Some("hexagonal") {
+            return Err(invalid("not a hexagonal map"));
+        }
+        if matches!(attribute("infinite").as_deref(), Some("1") | Some("true")) {
+            return Err(invalid("infinite maps are not supported"));
+        }
+
+        let number = |name: &str| -> io::Result<i32> {
+            attribute(name)
+                .and_then(|value| value.parse::<f64>().ok())
+                .map(|value| value as i32)
+                .ok_or_else(|| invalid(&format!("missing {}", name)))
+        };
+
+        let axis: OffsetAxis = match attribute("staggeraxis").as_deref() {
+            Some("x") => OffsetAxis::Q,
+            Some("y") => OffsetAxis::R,
+            _ => return Err(invalid("missing staggeraxis")),
+        };
+        let offset: i32 = match attribute("staggerindex").as_deref() {
+            Some("odd") => HEX_ODD,
+            Some("even") => HEX_EVEN,
+            _ => return Err(invalid("missing staggerindex")),
+        };
+
+        let width: i32 = number("width")?;
+        let height: i32 = number("height")?;
+        if width < 0 || height < 0 {
+            return Err(invalid("negative map size"));
+        }
+
+        Ok(TiledMap {
+            axis,
+            offset,
+            width,
+            height,
+            tile_width: number("tilewidth")?,
+            tile_height: number("tileheight")?,
+            hex_side_length: number("hexsidelength")?,
+            layers: vec![],
+        })
+    }
+
+    fn layer_from_gids(&self, name: String, gids: &[u32]) -> io::Result<TiledLayer> {
+        let size: i32 = self
+            .width
+            .checked_mul(self.height)
+            .ok_or_else(|| invalid("map size out of range"))?;
+        if gids.len() != size as usize {
+            return Err(invalid("layer data does not match the map size"));
+        }
+
+        let mut tiles: HashMap<Hex, u32> = HashMap::new();
+        for (i, gid) in gids.iter().enumerate() {
+            if *gid == 0 {
+                continue;
+            }
+            let coord: OffsetCoord = OffsetCoord {
+                col: i as i32 % self.width,
+                row: i as i32 / self.width,
+            };
+            tiles.insert(HexOffset::to_cube(self.axis, self.offset, coord), *gid);
+        }
+
+        Ok(TiledLayer { name, tiles })
+    }
+
+    fn gid_rows(&self, layer: &TiledLayer) -> Vec<Vec<u32>> {
+        (0..self.height)
+            .map(|row| {
+                (0..self.width)
+                    .map(|col| {
+                        let hex: Hex =
+                            HexOffset::to_cube(self.axis, self.offset, OffsetCoord { col, row });
+                        layer.tiles.get(&hex).copied().unwrap_or(0)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn stagger_axis(&self) -> &'static str {
+        match self.axis {
+            OffsetAxis::Q => "x",
+            OffsetAxis::R => "y",
+        }
+    }
+
+    fn stagger_index(&self) -> &'static str {
+        if self.offset == HEX_EVEN {
+            "even"
+        } else {
+            "odd"
+        }
+    }
+}
+
+const JSON_MAX_DEPTH: usize = 64;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub(crate) fn json_escape(text: &str) -> String {
+    let mut out: String = String::new();
+    for c in text.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// Finds the next `<name ...>` start tag and returns its attributes and the
+// text following it.
+fn xml_tag<'a>(
+    text: &'a str,
+    name: &str,
+) -> io::Result<Option<(HashMap<String, String>, &'a str)>> {
+    let open: String = format!("<{}", name);
+    let mut from: usize = 0;
+
+    loop {
+        let start: usize = match text[from..].find(&open) {
+            Some(found) => from + found,
+            None => return Ok(None),
+        };
+        let after_name: &str = &text[start + open.len()..];
+        if !after_name.starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+            from = start + open.len();
+            continue;
+        }
+
+        let end: usize = after_name
+            .find('>')
+            .ok_or_else(|| invalid("unterminated xml tag"))?;
+        let mut attributes: HashMap<String, String> = HashMap::new();
+        let mut rest: &str = after_name[..end].trim_end_matches('/');
+        while let Some(equals) = rest.find('=') {
+            let key: &str = rest[..equals].trim();
+            let value: &str = rest[equals + 1..].trim_start();
+            let quote: char = match value.chars().next() {
+                Some(quote) if quote == '"' || quote == '\'' => quote,
+                _ => return Err(invalid("unquoted xml attribute")),
+            };
+            let quoted: &str = &value[quote.len_utf8()..];
+            let close: usize = quoted
+                .find(quote)
+                .ok_or_else(|| invalid("unterminated xml attribute"))?;
+            attributes.insert(key.to_string(), unescape(&quoted[..close]));
+            rest = &quoted[close + quote.len_utf8()..];
+        }
+
+        return Ok(Some((attributes, &after_name[end + 1..])));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    text: &'a [u8],
+    position: usize,
+    depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(text: &'a str) -> JsonParser<'a> {
+        JsonParser {
+            text: text.as_bytes(),
+            position: 0,
+            depth: 0,
+        }
+    }
+
+    fn parse(&mut self) -> io::Result<Json> {
+        let value: Json = self.value()?;
+        self.skip_whitespace();
+        if self.position != self.text.len() {
+            return Err(invalid("trailing characters after json"));
+        }
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.position < self.text.len() && self.text[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str) -> io::Result<()> {
+        if self.text[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(())
+        } else {
+            Err(invalid("malformed json"))
+        }
+    }
+
+    fn value(&mut self) -> io::Result<Json> {
+        self.skip_whitespace();
+        match self.text.get(self.position) {
+            Some(b'{') => self.nested(Self::object),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b't') => self.expect("true").map(|_| Json::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Json::Bool(false)),
+            Some(b'n') => self.expect("null").map(|_| Json::Null),
+            Some(_) => self.number(),
+            None => Err(invalid("unexpected end of json")),
+        }
+    }
+
+    // Limits the nesting so deep input fails instead of overflowing the stack.
+    fn nested<F>(&mut self, parse: F) -> io::Result<Json>
+    where
+        F: Fn(&mut Self) -> io::Result<Json>,
+    {
+        if self.depth == JSON_MAX_DEPTH {
+            return Err(invalid("json nested too deeply"));
+        }
+        self.depth += 1;
+        let value: io::Result<Json> = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> io::Result<Json> {
+        self.expect("{")?;
+        let mut entries: Vec<(String, Json)> = vec![];
+        self.skip_whitespace();
+        if self.expect("}").is_ok() {
+            return Ok(Json::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key: String = self.string()?;
+            self.skip_whitespace();
+            self.expect(":")?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            if self.expect(",").is_err() {
+                self.expect("}")?;
+                return Ok(Json::Object(entries));
+            }
+        }
+    }
+
+    fn array(&mut self) -> io::Result<Json> {
+        self.expect("[")?;
+        let mut values: Vec<Json> = vec![];
+        self.skip_whitespace();
+        if self.expect("]").is_ok() {
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            if self.expect(",").is_err() {
+                self.expect("]")?;
+                return Ok(Json::Array(values));
+            }
+        }
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        self.expect("\"")?;
+        let mut bytes: Vec<u8> = vec![];
+
+        loop {
+            let byte: u8 = *self
+                .text
+                .get(self.position)
+                .ok_or_else(|| invalid("unterminated json string"))?;
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped: u8 = *self
+                        .text
+                        .get(self.position)
+                        .ok_or_else(|| invalid("unterminated json string"))?;
+                    self.position += 1;
+                    match escaped {
+                        b'n' => bytes.push(b'\n'),
+                        b't' => bytes.push(b'\t'),
+                        b'r' => bytes.push(b'\r'),
+                        b'b' => bytes.push(8),
+                        b'f' => bytes.push(12),
+                        b'u' => {
+                            let hex: &[u8] = self
+                                .text
+                                .get(self.position..self.position + 4)
+                                .ok_or_else(|| invalid("bad json escape"))?;
+                            let code: u32 = std::str::from_utf8(hex)
+                                .ok()
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .ok_or_else(|| invalid("bad json escape"))?;
+                            let c: char = char::from_u32(code).unwrap_or('\u{fffd}');
+                            bytes.extend_from_slice(c.to_string().as_bytes());
+                            self.position += 4;
+                        }
+                        other => bytes.push(other),
+                    }
+                }
+                other => bytes.push(other),
+            }
+        }
+
+        String::from_utf8(bytes).map_err(|_| invalid("json string is not utf-8"))
+    }
+
+    fn number(&mut self) -> io::Result<Json> {
+        let start: usize = self.position;
+        while self.position < self.text.len()
+            && matches!(
+                self.text[self.position],
+                b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'
+            )
+        {
+            self.position += 1;
+        }
+
+        std::str::from_utf8(&self.text[start..self.position])
+            .ok()
+            .and_then(|number| number.parse::<f64>().ok())
+            .map(Json::Number)
+            .ok_or_else(|| invalid("malformed json"))
+    }
+}
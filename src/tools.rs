@@ -1,4 +1,6 @@
 use crate::hexagon::{DoubledCoord, Hex, OffsetCoord, HexMath, HEX_DIRECTIONS, HEX_DIAGONALS};
+use crate::layout::{LayoutTool, TransformedLayout};
+use crate::point::Point;
 
 pub const HEX_EVEN: i32 = 1;
 pub const HEX_ODD: i32 = -1;
@@ -17,11 +19,84 @@ impl HexDirection {
     pub fn diagonal_neighbor(hex: Hex, direction: i32) -> Hex {
         hex.add(HEX_DIAGONALS[direction as usize])
     }
+
+    /// The 60° sector around one of the six directions that `to` lies in as
+    /// seen from `from`, or the diagonal between two sectors. `None` when both
+    /// hexes are the same.
+    pub fn sector(from: Hex, to: Hex) -> Option<HexSector> {
+        let delta: Hex = to.sub(from);
+        let dots: Vec<i32> = HEX_DIRECTIONS
+            .iter()
+            .map(|d| d.q() * delta.q() + d.r() * delta.r() + d.s() * delta.s())
+            .collect();
+        let best: i32 = *dots.iter().max().unwrap();
+        if best == 0 {
+            return None;
+        }
+
+        let direction: usize = dots.iter().position(|dot| *dot == best).unwrap();
+        if dots[(direction + 1) % 6] == best {
+            Some(HexSector::Diagonal(direction as i32))
+        } else if dots[(direction + 5) % 6] == best {
+            Some(HexSector::Diagonal(((direction + 5) % 6) as i32))
+        } else {
+            Some(HexSector::Direction(direction as i32))
+        }
+    }
+
+    /// The direction closest to `to` as seen from `from`. On a diagonal the
+    /// direction with the same index as the diagonal wins.
+    pub fn nearest(from: Hex, to: Hex) -> Option<i32> {
+        match Self::sector(from, to)? {
+            HexSector::Direction(direction) => Some(direction),
+            HexSector::Diagonal(diagonal) => Some(diagonal),
+        }
+    }
+
+    /// Angle in radians from the center of `from` to the center of `to` in
+    /// the pixel space of the layout.
+    pub fn angle(layout: impl Into<TransformedLayout>, from: Hex, to: Hex) -> f64 {
+        let layout: TransformedLayout = layout.into();
+        let a: Point = LayoutTool::hex_to_pixel(layout, from);
+        let b: Point = LayoutTool::hex_to_pixel(layout, to);
+        (b.y - a.y).atan2(b.x - a.x)
+    }
+}
+
+/// Where one hex lies relative to another.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HexSector {
+    /// Strictly inside the sector around `HEX_DIRECTIONS[i]`.
+    Direction(i32),
+    /// Exactly on `HEX_DIAGONALS[i]`, between directions `i` and `i + 1`.
+    Diagonal(i32),
+}
+
+/// Selects between the `q_*` (columns shoved) and `r_*` (rows shoved)
+/// variants of the offset coordinate conversions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OffsetAxis {
+    Q,
+    R,
 }
 
 pub struct HexOffset;
 
 impl HexOffset {
+    pub fn from_cube(axis: OffsetAxis, offset: i32, hex: Hex) -> OffsetCoord {
+        match axis {
+            OffsetAxis::Q => Self::q_from_cube(offset, hex),
+            OffsetAxis::R => Self::r_from_cube(offset, hex),
+        }
+    }
+
+    pub fn to_cube(axis: OffsetAxis, offset: i32, coord: OffsetCoord) -> Hex {
+        match axis {
+            OffsetAxis::Q => Self::q_to_cube(offset, coord),
+            OffsetAxis::R => Self::r_to_cube(offset, coord),
+        }
+    }
+
     pub fn q_from_cube(offset: i32, hex: Hex) -> OffsetCoord {
         let col: i32 = hex.q();
         let row: i32 = hex.r() + (hex.q() + offset * (hex.q() & 1)) / 2 as i32;
@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+
+use crate::hexagon::{Hex, HexMath, HexUtility, OffsetCoord};
+use crate::tools::{HexDirection, HexOffset, OffsetAxis};
+
+/// A map that wraps around in offset coordinates.
+///
+/// `columns` set alone gives a cylinder wrapping horizontally, `rows` alone
+/// wraps vertically and both together make a torus. The shoved axis must have
+/// an even size along the wrapped direction, otherwise the seam does not line
+/// up with the hex grid. `WrapTool` panics on wraps breaking these rules or
+/// with sizes below 1.
+#[derive(Clone, Copy, Debug)]
+pub struct Wrap {
+    pub axis: OffsetAxis,
+    pub offset: i32,
+    pub columns: Option<i32>,
+    pub rows: Option<i32>,
+}
+
+pub struct WrapTool;
+
+impl WrapTool {
+    /// Moves `hex` into the base copy of the map, where the wrapped offset
+    /// coordinates lie in `0..columns` and `0..rows`.
+    pub fn normalize(wrap: Wrap, hex: Hex) -> Hex {
+        Self::check(wrap);
+        let mut coord: OffsetCoord = HexOffset::from_cube(wrap.axis, wrap.offset, hex);
+
+        if let Some(columns) = wrap.columns {
+            coord.col = coord.col.rem_euclid(columns);
+        }
+        if let Some(rows) = wrap.rows {
+            coord.row = coord.row.rem_euclid(rows);
+        }
+
+        HexOffset::to_cube(wrap.axis, wrap.offset, coord)
+    }
+
+    pub fn neighbor(wrap: Wrap, hex: Hex, direction: i32) -> Hex {
+        Self::normalize(wrap, HexDirection::neighbor(hex, direction))
+    }
+
+    pub fn distance(wrap: Wrap, a: Hex, b: Hex) -> i32 {
+        let from: Hex = Self::normalize(wrap, a);
+        from.distance(Self::nearest_copy(wrap, from, b))
+    }
+
+    /// Draws the shortest line from `a` to `b`, crossing the seam when that
+    /// is shorter. Every hex of the line is normalized.
+    pub fn line(wrap: Wrap, a: Hex, b: Hex) -> Vec<Hex> {
+        let from: Hex = Self::normalize(wrap, a);
+
+        from.line(Self::nearest_copy(wrap, from, b))
+            .into_iter()
+            .map(|hex| Self::normalize(wrap, hex))
+            .collect()
+    }
+
+    /// Returns the normalized hexes within `radius` of `center`. A hex reached
+    /// from both sides of the seam is only listed once.
+    pub fn range(wrap: Wrap, center: Hex, radius: i32) -> Vec<Hex> {
+        let mut seen: HashSet<Hex> = HashSet::new();
+
+        center
+            .range(radius)
+            .into_iter()
+            .map(|hex| Self::normalize(wrap, hex))
+            .filter(|hex| seen.insert(*hex))
+            .collect()
+    }
+
+    /// The cube vectors that move a hex by one whole map along each wrapped
+    /// axis.
+    pub fn translations(wrap: Wrap) -> Vec<Hex> {
+        Self::check(wrap);
+        let base: Hex = HexOffset::to_cube(wrap.axis, wrap.offset, OffsetCoord { col: 0, row: 0 });
+        let mut translations: Vec<Hex> = vec![];
+
+        if let Some(columns) = wrap.columns {
+            let coord: OffsetCoord = OffsetCoord {
+                col: columns,
+                row: 0,
+            };
+            translations.push(HexOffset::to_cube(wrap.axis, wrap.offset, coord).sub(base));
+        }
+        if let Some(rows) = wrap.rows {
+            let coord: OffsetCoord = OffsetCoord { col: 0, row: rows };
+            translations.push(HexOffset::to_cube(wrap.axis, wrap.offset, coord).sub(base));
+        }
+
+        translations
+    }
+
+    fn check(wrap: Wrap) {
+        if let Some(columns) = wrap.columns {
+            if columns < 1 {
+                panic!("columns must be at least 1");
+            }
+            if wrap.axis == OffsetAxis::Q && columns % 2 != 0 {
+                panic!("columns must be even when wrapping q offset coordinates");
+            }
+        }
+        if let Some(rows) = wrap.rows {
+            if rows < 1 {
+                panic!("rows must be at least 1");
+            }
+            if wrap.axis == OffsetAxis::R && rows % 2 != 0 {
+                panic!("rows must be even when wrapping r offset coordinates");
+            }
+        }
+    }
+
+    // Picks the copy of `b` closest to `from` among the neighboring copies of
+    // the map.
+    fn nearest_copy(wrap: Wrap, from: Hex, b: Hex) -> Hex {
+        let mut copies: Vec<Hex> = vec![Self::normalize(wrap, b)];
+
+        for translation in Self::translations(wrap) {
+            copies = copies
+                .into_iter()
+                .flat_map(|copy| (-1..=1).map(move |k| copy.add(translation.scale(k))))
+                .collect();
+        }
+
+        copies
+            .into_iter()
+            .min_by_key(|copy| (from.distance(*copy), copy.q(), copy.r()))
+            .unwrap()
+    }
+}